@@ -12,6 +12,7 @@ cargo run --features schema --bin schema
 This creates ~/Repos/quiet-consensus/poll/contract/schema/ like:
 
 schema/
+├── execute_answer.json
 ├── execute_msg.json
 ├── instantiate_msg.json
 ├── poll.json
@@ -24,7 +25,7 @@ schema/
 use std::env::current_dir;
 use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
-use polling_contract::msg::{InstantiateMsg, ExecuteMsg, QueryMsg, QueryWithPermit, QueryAnswer};
+use polling_contract::msg::{InstantiateMsg, ExecuteMsg, ExecuteAnswer, QueryMsg, QueryWithPermit, QueryAnswer};
 use polling_contract::state::Poll;
 
 fn main() {
@@ -45,6 +46,7 @@ fn main() {
     // Message schemas
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(ExecuteAnswer), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(QueryWithPermit), &out_dir);
     export_schema(&schema_for!(QueryAnswer), &out_dir);
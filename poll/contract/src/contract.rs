@@ -8,16 +8,66 @@ See `polling-contract-design.md` for more details.
 
 // Imports
 use cosmwasm_std::{
-    entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, StdError, CanonicalAddr, to_binary, WasmQuery, QueryRequest,
+    entry_point, Addr, BankMsg, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Response, StdResult, StdError, CanonicalAddr, Storage, Timestamp, to_binary, Uint128,
 };
-use serde::{Deserialize, Serialize};
-use secret_toolkit::permit::Permit;
-use crate::msg::{InstantiateMsg, ExecuteMsg, QueryMsg, QueryWithPermit, QueryAnswer};
-use crate::state::{SCT_CONTRACT_ADDRESS, SCT_CODE_HASH, POLL_COUNT, POLLS, VOTES, Poll};
+use secret_toolkit::storage::Keymap;
+use secret_toolkit::permit::{Permit, RevokedPermits};
+use secret_toolkit::utils::pad_handle_result;
+use secret_toolkit::viewing_key::{ViewingKey, ViewingKeyStore};
+use crate::eligibility::{VoterCredentials, check_creator_credential, check_voter_eligibility, get_breakdown_group, get_poll_communities, get_voter_weight, requires_default_sct, validate_breakdown, validate_eligibility_rule, validate_trait_weights};
+use crate::msg::{InstantiateMsg, ExecuteMsg, ExecuteAnswer, QueryMsg, QueryWithPermit, QueryAnswer, QueryAuth, Ballot, PollPermissions, PollVote, PublicAuthor, Organiser, CommunityConfig, Question};
+use crate::state::{SCT_CONTRACT_ADDRESS, SCT_CODE_HASH, POLL_COUNT, POLLS, POLL_RESULTS, BREAKDOWN_COUNTS, CROSS_TAB_COUNTS, DRAFTS, POLL_REVISIONS, EDITED_CONTENT, VOTES, SURVEY_BALLOTS, FLAGS, VOTED_POLLS, POLL_CREATORS, CREATED_POLLS, NULLIFIER_SECRET, PREFIX_REVOKED_PERMITS, ADMIN, ORGANISERS, CONFIG, COMMUNITY_CREATION_RULES, TREASURY, POLL_DEPOSITS, CREATOR_ACTIVITY, MODERATORS, AUDIT_LOG, AuditEntry, Breakdown, CommunityRule, Config, CrossTab, CrossTabCounts, CrossTabResult, ModerationAction, PollDraft, PollResult, PollRevision, EligibilityRule, PollDeposit, Treasury, Poll, PollAuthor, SurveyQuestion, TraitWeights, VotingMethod};
+use serde::{Serialize, de::DeserializeOwned};
 use sha2::{Sha256, Digest};
 use hex;
 
 
+// Vote responses are padded to a multiple of this many bytes, so their size doesn't depend on the vote
+pub const BLOCK_SIZE: usize = 256;
+
+// Maximum number of votes returned by one GetMyVotes/GetMyVotedPolls query
+pub const MAX_VOTES_PER_QUERY: u32 = 50;
+
+// Maximum number of polls returned by one GetMyPolls query
+pub const MAX_POLLS_PER_QUERY: u32 = 20;
+
+// Denomination of poll creation fees and deposits
+pub const FEE_DENOM: &str = "uscrt";
+
+// Longest a poll can stay open for, if it has a duration (1 year)
+pub const MAX_POLL_DURATION: u64 = 365 * 24 * 60 * 60;
+
+// Highest flag reason code (0 spam, 1 abusive, 2 misleading, 3 off-topic, 4 other)
+pub const MAX_FLAG_REASON: u8 = 4;
+
+// Maximum number of audit log entries returned by one GetAuditLog query
+pub const MAX_AUDIT_ENTRIES_PER_QUERY: u32 = 50;
+
+// Maximum number of moderators
+pub const MAX_MODERATORS: usize = 20;
+
+// Maximum number of votes, ballots, flags and revisions removed by one ArchivePoll call
+pub const MAX_ARCHIVE_BATCH: u32 = 100;
+
+// Maximum number of questions in a survey
+pub const MAX_SURVEY_QUESTIONS: usize = 20;
+
+// Smallest group of respondents whose answers can be reported together (k-anonymity)
+pub const MIN_GROUP_SIZE: u32 = 5;
+
+// Maximum number of cross-tabs a survey can have
+pub const MAX_CROSS_TABS: usize = 10;
+
+// Maximum number of drafts one creator can have
+pub const MAX_DRAFTS: u32 = 20;
+
+// Maximum number of times a poll can be edited
+pub const MAX_POLL_EDITS: u32 = 20;
+
+// Maximum number of addresses registered for one organiser
+pub const MAX_ORGANISER_ADDRESSES: usize = 10;
+
+
 
 
 
@@ -31,18 +81,52 @@ use hex;
 //    msg - an InstantiateMsg as defined in msg.rs
 
 #[entry_point]
-pub fn instantiate( deps: DepsMut, _env: Env, _info: MessageInfo, msg: InstantiateMsg) -> StdResult<Response> {
+pub fn instantiate( deps: DepsMut, env: Env, info: MessageInfo, msg: InstantiateMsg) -> StdResult<Response> {
+
+    // Set the admin (defaults to the instantiator)
+    let admin = msg.admin.unwrap_or(info.sender);
+    ADMIN.save(deps.storage, &deps.api.addr_canonicalize(admin.as_str())?)?;
+
+    // Set the config
+    let config = Config {
+        require_sct_to_create: msg.require_sct_to_create.unwrap_or(false),
+        creation_fee: msg.creation_fee.unwrap_or_default(),
+        creation_deposit: msg.creation_deposit.unwrap_or_default(),
+        deposit_refund_turnout: msg.deposit_refund_turnout.unwrap_or(0),
+        max_polls_per_window: msg.max_polls_per_window.unwrap_or(0),
+        rate_limit_window: msg.rate_limit_window.unwrap_or(0),
+        max_open_polls: msg.max_open_polls.unwrap_or(0),
+        flag_threshold: msg.flag_threshold.unwrap_or(0),
+    };
+    validate_rate_limit(&config)?;
+    CONFIG.save(deps.storage, &config)?;
+    TREASURY.save(deps.storage, &Treasury::default())?;
+    MODERATORS.save(deps.storage, &Vec::new())?;
 
     // Set the SCT contract address and code hash
     let sct_contract_address = deps.api.addr_canonicalize(msg.sct_contract_address.as_str())?;
     SCT_CONTRACT_ADDRESS.save(deps.storage, &sct_contract_address)?;
     SCT_CODE_HASH.save(deps.storage, &msg.sct_code_hash)?;
 
+    // Create the secret for voter nullifiers from the block's randomness
+    let random = env.block.random
+        .ok_or_else(|| StdError::generic_err("Block randomness is unavailable"))?;
+    let nullifier_secret = Sha256::digest(random.as_slice()).to_vec();
+    NULLIFIER_SECRET.save(deps.storage, &nullifier_secret)?;
+
+    // Seed the viewing key generator with the block's randomness too
+    ViewingKey::set_seed(deps.storage, random.as_slice());
+
     // Set the poll count to 0
     POLL_COUNT.save(deps.storage, &0u32)?;
 
     // POLLS Keymap is empty by default, no initialization needed
     // VOTES Keymap is empty by default, no initialization needed
+    // ORGANISERS Keymap is empty by default, no initialization needed
+    // COMMUNITY_CREATION_RULES Keymap is empty by default, no initialization needed
+    // POLL_DEPOSITS Keymap is empty by default, no initialization needed
+    // CREATOR_ACTIVITY Keymap is empty by default, no initialization needed
+    // AUDIT_LOG AppendStore is empty by default, no initialization needed
 
     Ok(Response::default())
 }
@@ -63,33 +147,225 @@ pub fn instantiate( deps: DepsMut, _env: Env, _info: MessageInfo, msg: Instantia
 pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
     match msg {
         // Create poll 
-        // (any user)
-        ExecuteMsg::MakePoll { title, description, options } => {
-            try_make_poll(deps, env, title, description, options)
+        // (any user, or only SCT holders of the poll's communities if the config requires it)
+        ExecuteMsg::MakePoll { title, description, options, questions, cross_tabs, eligibility, trait_weights, breakdown, public_author, duration_seconds, draft, sct_auth, credentials } => {
+            let creator_credentials = sct_auth.map(|sct_auth| VoterCredentials {
+                sct_auth,
+                snip20_auth: None,
+                merkle_proof: Vec::new(),
+                credentials: credentials.unwrap_or_default(),
+            });
+            try_make_poll(deps, env, info, title, description, options, questions.unwrap_or_default(), cross_tabs.unwrap_or_default(), eligibility, trait_weights, breakdown, public_author, duration_seconds, draft.unwrap_or(false), creator_credentials)
+        }
+        // Publish a draft
+        // (the draft's creator, who must meet the same requirements as for MakePoll)
+        ExecuteMsg::PublishPoll { poll_id, sct_auth, credentials } => {
+            let creator_credentials = sct_auth.map(|sct_auth| VoterCredentials {
+                sct_auth,
+                snip20_auth: None,
+                merkle_proof: Vec::new(),
+                credentials: credentials.unwrap_or_default(),
+            });
+            try_publish_poll(deps, env, info, poll_id, creator_credentials)
+        }
+        // Edit poll
+        // (the poll's creator, before anyone has voted)
+        ExecuteMsg::EditPoll { poll_id, title, description, options } => {
+            try_edit_poll(deps, env, info, poll_id, title, description, options)
+        }
+        // Archive a closed poll
+        // (anyone)
+        ExecuteMsg::ArchivePoll { poll_id, limit } => {
+            try_archive_poll(deps, env, poll_id, limit.unwrap_or(MAX_ARCHIVE_BATCH))
         }
+        // Delete a poll without votes, or a draft
+        // (the poll's creator)
+        ExecuteMsg::DeletePoll { poll_id } => try_delete_poll(deps, info, poll_id),
         // Cast vote 
-        // (only SCT holders)
-        ExecuteMsg::CastVote { poll_id, option_idx, sct_viewing_key } => {
-            try_cast_vote(deps, info, poll_id, option_idx, sct_viewing_key)
+        // (only users meeting the poll's eligibility rules, SCT holders by default)
+        ExecuteMsg::CastVote { poll_id, revision, option_idx, sct_viewing_key, snip20_auth, merkle_proof, credentials, hide_option, .. } => {
+            let credentials = VoterCredentials {
+                sct_auth: QueryAuth::ViewingKey { key: sct_viewing_key },
+                snip20_auth,
+                merkle_proof: merkle_proof.unwrap_or_default(),
+                credentials: credentials.unwrap_or_default(),
+            };
+            let hide_option = hide_option.unwrap_or(false);
+            pad_handle_result(try_cast_vote(deps, &env, info.sender, poll_id, revision, option_idx, credentials, hide_option), BLOCK_SIZE)
+        }
+        // Submit a survey ballot
+        // (only users meeting the survey's eligibility rules, SCT holders by default)
+        ExecuteMsg::SubmitBallot { poll_id, answers, sct_viewing_key, snip20_auth, merkle_proof, credentials, .. } => {
+            let credentials = VoterCredentials {
+                sct_auth: QueryAuth::ViewingKey { key: sct_viewing_key },
+                snip20_auth,
+                merkle_proof: merkle_proof.unwrap_or_default(),
+                credentials: credentials.unwrap_or_default(),
+            };
+            pad_handle_result(try_submit_ballot(deps, env, info, poll_id, answers, credentials), BLOCK_SIZE)
+        }
+        // Cast vote through a relayer
+        // (anyone can relay, the ballot's signer must meet the poll's eligibility rules)
+        ExecuteMsg::RelayVote { ballot, sct_permit, snip20_auth, merkle_proof, credentials, hide_option, .. } => {
+            let credentials = VoterCredentials {
+                sct_auth: QueryAuth::Permit { permit: sct_permit },
+                snip20_auth,
+                merkle_proof: merkle_proof.unwrap_or_default(),
+                credentials: credentials.unwrap_or_default(),
+            };
+            pad_handle_result(try_relay_vote(deps, env, ballot, credentials, hide_option.unwrap_or(false)), BLOCK_SIZE)
+        }
+        // Flag a poll
+        // (only users meeting the poll's eligibility rules, once per poll)
+        ExecuteMsg::FlagPoll { poll_id, reason_code, sct_viewing_key, snip20_auth, merkle_proof, credentials, .. } => {
+            let credentials = VoterCredentials {
+                sct_auth: QueryAuth::ViewingKey { key: sct_viewing_key },
+                snip20_auth,
+                merkle_proof: merkle_proof.unwrap_or_default(),
+                credentials: credentials.unwrap_or_default(),
+            };
+            pad_handle_result(try_flag_poll(deps, info, poll_id, reason_code, credentials), BLOCK_SIZE)
+        }
+        // Hide a poll
+        // (moderators and admin only)
+        ExecuteMsg::HidePoll { poll_id, reason } => {
+            try_moderate_poll(deps, env, info, poll_id, ModerationAction::Hide, reason)
+        }
+        // Restore a poll
+        // (moderators and admin only)
+        ExecuteMsg::RestorePoll { poll_id, reason } => {
+            try_moderate_poll(deps, env, info, poll_id, ModerationAction::Restore, reason)
+        }
+        // Replace the moderators
+        // (admin only)
+        ExecuteMsg::SetModerators { moderators } => try_set_moderators(deps, info, moderators),
+        // Register, update or remove a verified organiser
+        // (admin only)
+        ExecuteMsg::SetOrganiser { name, addresses } => try_set_organiser(deps, info, name, addresses),
+        // Change the config
+        // (admin only)
+        ExecuteMsg::SetConfig {
+            require_sct_to_create,
+            creation_fee,
+            creation_deposit,
+            deposit_refund_turnout,
+            max_polls_per_window,
+            rate_limit_window,
+            max_open_polls,
+            flag_threshold,
+        } => try_set_config(
+            deps,
+            info,
+            require_sct_to_create,
+            creation_fee,
+            creation_deposit,
+            deposit_refund_turnout,
+            max_polls_per_window,
+            rate_limit_window,
+            max_open_polls,
+            flag_threshold,
+        ),
+        // Override the config for a community
+        // (admin only)
+        ExecuteMsg::SetCommunityConfig { community, code_hash, require_sct_to_create } => {
+            try_set_community_config(deps, info, community, code_hash, require_sct_to_create)
+        }
+        // Reclaim a poll's deposit
+        // (the poll's depositor)
+        ExecuteMsg::ClaimDeposit { poll_id } => try_claim_deposit(deps, info, poll_id),
+        // Withdraw collected fees
+        // (admin only)
+        ExecuteMsg::WithdrawFees { amount, recipient } => try_withdraw_fees(deps, info, amount, recipient),
+        // Change the admin
+        // (admin only)
+        ExecuteMsg::ChangeAdmin { admin } => try_change_admin(deps, info, admin),
+        // Revoke a permit
+        // (anyone, for their own permits)
+        ExecuteMsg::RevokePermit { permit_name } => try_revoke_permit(deps, info, permit_name),
+        // Create a random viewing key
+        // (anyone, for themselves)
+        ExecuteMsg::CreateViewingKey { entropy, .. } => {
+            pad_handle_result(try_create_viewing_key(deps, env, info, entropy), BLOCK_SIZE)
+        }
+        // Set a chosen viewing key
+        // (anyone, for themselves)
+        ExecuteMsg::SetViewingKey { key, .. } => {
+            pad_handle_result(try_set_viewing_key(deps, info, key), BLOCK_SIZE)
         }
     }
 }
 
 // try_make_poll creates a new poll with the given title, description, and options
 // It generates a unique poll_id using SHA-256 hash of title+options
+// Polls without eligibility rules are open to any SCT holder
+// Surveys have questions instead of options, and their poll_id also hashes every question and its options
+// Surveys can also list pairs of questions to cross-tabulate, counted as ballots are submitted
+// Drafts are saved privately for the creator instead (see `try_publish_poll`)
+// The creator is recorded privately (see `POLL_CREATORS`), and is only shown publicly if public_author is set
+// Fails if:
+//    - public_author is an organiser the sender isn't registered for
+//    - one of the poll's communities requires an SCT to create polls and the sender doesn't hold one
+//    - the sender didn't send exactly the creation fee plus deposit
+//    - the sender has hit the rate limit or open poll quota
+//    - duration_seconds is 0 or more than MAX_POLL_DURATION, or isn't set while max_open_polls is
+//    - funds are sent with a draft
+#[allow(clippy::too_many_arguments)]
 pub fn try_make_poll(
     deps: DepsMut,
     env: Env,
+    info: MessageInfo,
     title: String,
     description: String,
     options: Vec<String>,
+    questions: Vec<Question>,
+    cross_tabs: Vec<CrossTab>,
+    eligibility: Option<EligibilityRule>,
+    trait_weights: Option<TraitWeights>,
+    breakdown: Option<Breakdown>,
+    public_author: Option<PublicAuthor>,
+    duration_seconds: Option<u64>,
+    draft: bool,
+    creator_credentials: Option<VoterCredentials>,
 ) -> StdResult<Response> {
     
+    let questions: Vec<SurveyQuestion> = questions
+        .into_iter()
+        .map(|question| SurveyQuestion {
+            tally: vec![Uint128::zero(); question.options.len()],
+            question: question.question,
+            options: question.options,
+            method: question.method.unwrap_or(VotingMethod::SingleChoice),
+            condition: question.condition,
+            respondents: 0,
+        })
+        .collect();
+    
     // Generate poll_id
-    let poll_id = generate_poll_id(&title, &options);
+    let poll_id = generate_content_id(&title, &options, &questions);
     
     // Validate the poll
-    validate_poll(&title, &description, &options, &poll_id, deps.as_ref())?;
+    validate_poll(&title, &description, &options, &questions, &poll_id, deps.as_ref())?;
+    validate_cross_tabs(&questions, &cross_tabs)?;
+    let eligibility = eligibility.unwrap_or(EligibilityRule::HoldsNft { contract: None });
+    validate_eligibility_rule(&eligibility, &trait_weights, deps.as_ref())?;
+    if let Some(trait_weights) = &trait_weights {
+        validate_trait_weights(trait_weights)?;
+    }
+    if let Some(breakdown) = &breakdown {
+        if !questions.is_empty() {
+            return Err(StdError::generic_err("Surveys can't have a breakdown, use cross-tabs instead"));
+        }
+        validate_breakdown(breakdown)?;
+        if !requires_default_sct(deps.as_ref(), &eligibility)? {
+            return Err(StdError::generic_err("Polls with a breakdown must require the default SCT, as groups are read from it"));
+        }
+    }
+    let author = get_poll_author(deps.as_ref(), &info.sender, public_author)?;
+    if let Some(duration_seconds) = duration_seconds {
+        if duration_seconds == 0 || duration_seconds > MAX_POLL_DURATION {
+            return Err(StdError::generic_err("Invalid poll duration (min 1 second, max 1 year)"));
+        }
+    }
     
     // Create the poll
     let tally_size = options.len();
@@ -99,11 +375,134 @@ pub fn try_make_poll(
         description,
         created_at: env.block.time,
         options,
-        tally: vec![0; tally_size], // Initialize tally with zeros
+        tally: vec![Uint128::zero(); tally_size], // Initialize tally with zeros
+        questions,
+        cross_tabs,
+        eligibility,
+        trait_weights,
+        breakdown,
+        author,
+        closes_at: None,
+        flags: 0,
+        moderation: None,
+        edits: 0,
     };
     
+    // Save drafts privately
+    // Drafts are paid for when they're published, so funds sent now would be stranded
+    if draft {
+        if !info.funds.is_empty() {
+            return Err(StdError::generic_err("Drafts don't take funds, send the creation fee and deposit with PublishPoll"));
+        }
+        let creator_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+        let creator = voter_nullifier(deps.as_ref(), "creator", &creator_canonical)?;
+        let drafts = DRAFTS.add_suffix(creator.as_bytes());
+        if drafts.contains(deps.storage, &poll_id) {
+            return Err(StdError::generic_err("You already have a draft with this poll ID (same title and options)"));
+        }
+        if drafts.get_len(deps.storage)? >= MAX_DRAFTS {
+            return Err(StdError::generic_err(format!("Too many drafts (max {})", MAX_DRAFTS)));
+        }
+        drafts.insert(deps.storage, &poll_id, &PollDraft { poll, duration_seconds })?;
+        return Ok(Response::new().add_attribute("action", "make_draft").add_attribute("poll_id", poll_id));
+    }
+    
+    publish_poll(deps, env, info, poll, duration_seconds, creator_credentials)
+}
+
+// try_publish_poll makes one of the sender's drafts a live poll
+// The poll's creation time and duration start now
+// Fails if:
+//    - the sender has no draft with this poll_id
+//    - a live poll already has this poll_id
+//    - any of the requirements for creating a poll aren't met (see `publish_poll`)
+pub fn try_publish_poll(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    creator_credentials: Option<VoterCredentials>,
+) -> StdResult<Response> {
+    
+    let creator_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let creator = voter_nullifier(deps.as_ref(), "creator", &creator_canonical)?;
+    let drafts = DRAFTS.add_suffix(creator.as_bytes());
+    let draft = drafts.get(deps.storage, &poll_id)
+        .ok_or_else(|| StdError::generic_err("Draft does not exist"))?;
+    check_content_available(deps.as_ref(), &poll_id)?;
+    
+    let mut poll = draft.poll;
+    poll.created_at = env.block.time;
+    let response = publish_poll(deps.branch(), env, info, poll, draft.duration_seconds, creator_credentials)?;
+    drafts.remove(deps.storage, &poll_id)?;
+    
+    Ok(response)
+}
+
+// Helper function to make a poll live, once the creator meets the requirements for creating it
+// Fails if:
+//    - a live poll has the same title and options (drafts can be edited after their poll_id is generated)
+//    - one of the poll's communities requires an SCT to create polls and the sender doesn't hold one
+//    - the sender didn't send exactly the creation fee plus deposit
+//    - the sender has hit the rate limit or open poll quota
+fn publish_poll(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mut poll: Poll,
+    duration_seconds: Option<u64>,
+    creator_credentials: Option<VoterCredentials>,
+) -> StdResult<Response> {
+    
+    let poll_id = poll.poll_id.clone();
+    let closes_at = duration_seconds.map(|duration_seconds| env.block.time.plus_seconds(duration_seconds));
+    poll.closes_at = closes_at;
+    let content_id = generate_content_id(&poll.title, &poll.options, &poll.questions);
+    if content_id != poll_id {
+        check_content_available(deps.as_ref(), &content_id)?;
+    }
+    
+    // Check the creator holds an SCT of each of the poll's communities that requires one
+    for community in get_poll_communities(deps.as_ref(), &poll.eligibility)? {
+        if requires_sct_to_create(deps.as_ref(), &community.address)? {
+            let creator_credentials = creator_credentials.as_ref()
+                .ok_or_else(|| StdError::generic_err("This community requires an SCT to create polls, sct_auth is required"))?;
+            check_creator_credential(deps.as_ref(), &info.sender, &community, creator_credentials)?;
+        }
+    }
+    
+    // Check the creation fee and deposit were paid
+    let config = CONFIG.load(deps.storage)?;
+    check_creation_payment(&info.funds, &config)?;
+    
+    // Check (and update) the creator's rate limit and open poll quota
+    let creator_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let creator = voter_nullifier(deps.as_ref(), "creator", &creator_canonical)?;
+    record_creator_activity(deps.branch(), &config, &creator, &poll_id, env.block.time, closes_at)?;
+    
     // Save the poll
     POLLS.insert(deps.storage, &poll_id, &poll)?;
+    if content_id != poll_id {
+        EDITED_CONTENT.insert(deps.storage, &content_id, &poll_id)?;
+    }
+    
+    // Record the creator privately
+    POLL_CREATORS.insert(deps.storage, &poll_id, &creator)?;
+    CREATED_POLLS.add_suffix(creator.as_bytes()).push(deps.storage, &poll_id)?;
+    
+    // Keep the fee in the treasury and hold the deposit for the poll
+    let mut treasury = TREASURY.load(deps.storage)?;
+    treasury.fees += config.creation_fee;
+    if !config.creation_deposit.is_zero() {
+        let deposit = PollDeposit {
+            depositor: creator.clone(),
+            amount: config.creation_deposit,
+            refund_turnout: config.deposit_refund_turnout,
+        };
+        POLL_DEPOSITS.insert(deps.storage, &poll_id, &deposit)?;
+        treasury.deposits_held += config.creation_deposit;
+    }
+    TREASURY.save(deps.storage, &treasury)?;
     
     // Increment poll count
     let current_count = POLL_COUNT.load(deps.storage)?;
@@ -112,57 +511,800 @@ pub fn try_make_poll(
     Ok(Response::new().add_attribute("action", "make_poll").add_attribute("poll_id", poll_id))
 }
 
-// try_cast_vote allows a user with an SCT to vote on an existing poll
-// It checks for SCT ownership, prevents double voting, and updates the tally
-pub fn try_cast_vote(
+// try_edit_poll replaces a poll's title, description or options, keeping its poll_id
+// The previous content is added to the poll's revisions
+// Fails if:
+//    - the poll doesn't exist
+//    - the sender isn't the poll's creator
+//    - the poll has any votes
+//    - the poll has been edited MAX_POLL_EDITS times
+//    - the new content is invalid (see validate_poll_contents)
+//    - another live poll has the new title and options
+pub fn try_edit_poll(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     poll_id: String,
-    option_idx: u32,
-    sct_viewing_key: String,
+    title: Option<String>,
+    description: Option<String>,
+    options: Option<Vec<String>>,
 ) -> StdResult<Response> {
     
-    let sender_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
-    
-    // Check if user owns an SCT
-    match address_owns_sct(deps.as_ref(), &sender_canonical, &sct_viewing_key) {
-        Ok(true) => {
-            // User has SCT, continue with voting
+    // Drafts are edited in place
+    let creator_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let drafts = DRAFTS.add_suffix(voter_nullifier(deps.as_ref(), "creator", &creator_canonical)?.as_bytes());
+    if let Some(mut draft) = drafts.get(deps.storage, &poll_id) {
+        if let Some(title) = title {
+            draft.poll.title = title;
+        }
+        if let Some(description) = description {
+            draft.poll.description = description;
         }
-        Ok(false) => {
-            // This case should not happen with the new implementation
-            return Err(StdError::generic_err("SCT check returned false unexpectedly"));
+        if let Some(options) = options {
+            draft.poll.tally = vec![Uint128::zero(); options.len()];
+            draft.poll.options = options;
         }
-        Err(e) => {
-            // User doesn't have SCT or query failed
-            return Err(e);
+        validate_poll_contents(&draft.poll.title, &draft.poll.description, &draft.poll.options, &draft.poll.questions)?;
+        drafts.insert(deps.storage, &poll_id, &draft)?;
+        return Ok(Response::new().add_attribute("action", "edit_draft").add_attribute("poll_id", poll_id));
+    }
+    
+    let mut poll = POLLS.get(deps.storage, &poll_id)
+        .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+    check_creator(deps.as_ref(), &poll_id, &info.sender)?;
+    if has_votes(&poll) {
+        return Err(StdError::generic_err("Polls can't be edited after the first vote"));
+    }
+    if poll.edits >= MAX_POLL_EDITS {
+        return Err(StdError::generic_err(format!("Poll has been edited too many times (max {})", MAX_POLL_EDITS)));
+    }
+    
+    // Check the new content
+    let title = title.unwrap_or_else(|| poll.title.clone());
+    let description = description.unwrap_or_else(|| poll.description.clone());
+    let options = options.unwrap_or_else(|| poll.options.clone());
+    validate_poll_contents(&title, &description, &options, &poll.questions)?;
+    let content_id = generate_content_id(&title, &options, &poll.questions);
+    if content_id != poll_id && content_id != generate_content_id(&poll.title, &poll.options, &poll.questions) {
+        check_content_available(deps.as_ref(), &content_id)?;
+    }
+    
+    // Move the poll's entry in the duplicate content index to the new content
+    release_content_id(deps.storage, &poll)?;
+    if content_id != poll_id {
+        EDITED_CONTENT.insert(deps.storage, &content_id, &poll_id)?;
+    }
+    
+    // Keep the previous content as a revision
+    let revision = PollRevision {
+        edited_at: env.block.time,
+        title: std::mem::replace(&mut poll.title, title),
+        description: std::mem::replace(&mut poll.description, description),
+        options: std::mem::replace(&mut poll.options, options),
+    };
+    POLL_REVISIONS.add_suffix(poll_id.as_bytes()).push(deps.storage, &revision)?;
+    
+    // Update the poll
+    poll.tally = vec![Uint128::zero(); poll.options.len()];
+    poll.edits += 1;
+    POLLS.insert(deps.storage, &poll_id, &poll)?;
+    
+    Ok(Response::new().add_attribute("action", "edit_poll").add_attribute("poll_id", poll_id))
+}
+
+// try_archive_poll replaces a closed poll with its final result (dropping it from its creator's polls),
+// then removes its votes, survey ballots, flags and revisions in batches of up to limit entries
+// Call it again until the response's cleaned_up attribute is true
+// Fails if:
+//    - the poll doesn't exist (and wasn't archived)
+//    - the poll is still open, or never closes
+//    - the limit is 0 or more than MAX_ARCHIVE_BATCH
+//    - the poll was already archived and cleaned up
+pub fn try_archive_poll(
+    deps: DepsMut,
+    env: Env,
+    poll_id: String,
+    limit: u32,
+) -> StdResult<Response> {
+    
+    if limit == 0 || limit > MAX_ARCHIVE_BATCH {
+        return Err(StdError::generic_err(format!("Invalid limit (min 1, max {})", MAX_ARCHIVE_BATCH)));
+    }
+    let poll_votes = VOTES.add_suffix(poll_id.as_bytes());
+    let poll_ballots = SURVEY_BALLOTS.add_suffix(poll_id.as_bytes());
+    
+    // Replace the poll with its final result the first time it's archived
+    let mut result = match POLL_RESULTS.get(deps.storage, &poll_id) {
+        Some(result) => result,
+        None => {
+            let poll = POLLS.get(deps.storage, &poll_id)
+                .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+            let closed_at = match poll.closes_at {
+                Some(closes_at) if !is_poll_open(&poll, env.block.time) => closes_at,
+                _ => return Err(StdError::generic_err("Only closed polls can be archived")),
+            };
+            let breakdown = get_breakdown_cells(deps.storage, &poll).ok().map(|(_, cells)| cells);
+            let cross_tab_counts = CROSS_TAB_COUNTS.get(deps.storage, &poll_id)
+                .unwrap_or_else(|| new_cross_tab_counts(&poll));
+            let cross_tabs = poll.cross_tabs.iter().zip(cross_tab_counts)
+                .filter_map(|(cross_tab, tab_counts)| {
+                    get_cross_tab_cells(tab_counts).ok().map(|(respondents, cells)| CrossTabResult {
+                        row_question: cross_tab.row_question,
+                        column_question: cross_tab.column_question,
+                        respondents,
+                        cells,
+                    })
+                })
+                .collect();
+            POLLS.remove(deps.storage, &poll_id)?;
+            release_content_id(deps.storage, &poll)?;
+            release_open_poll(deps.storage, &poll_id)?;
+            remove_poll_creator(deps.storage, &poll_id)?;
+            if BREAKDOWN_COUNTS.contains(deps.storage, &poll_id) {
+                BREAKDOWN_COUNTS.remove(deps.storage, &poll_id)?;
+            }
+            if CROSS_TAB_COUNTS.contains(deps.storage, &poll_id) {
+                CROSS_TAB_COUNTS.remove(deps.storage, &poll_id)?;
+            }
+            let current_count = POLL_COUNT.load(deps.storage)?;
+            POLL_COUNT.save(deps.storage, &(current_count - 1))?;
+            PollResult {
+                poll_id: poll_id.clone(),
+                title: poll.title,
+                description: poll.description,
+                options: poll.options,
+                tally: poll.tally,
+                questions: poll.questions,
+                created_at: poll.created_at,
+                closed_at,
+                voters: get_turnout(deps.storage, &poll_id)?,
+                breakdown,
+                cross_tabs,
+                cleaned_up: false,
+            }
         }
+    };
+    if result.cleaned_up {
+        return Err(StdError::generic_err("Poll has already been archived"));
+    }
+    
+    // Remove a batch of votes, then ballots, then flags, then revisions
+    let mut removed = remove_keymap_entries(deps.storage, &poll_votes, limit)?;
+    removed += remove_keymap_entries(deps.storage, &poll_ballots, limit - removed)?;
+    removed += remove_keymap_entries(deps.storage, &FLAGS.add_suffix(poll_id.as_bytes()), limit - removed)?;
+    let revisions = POLL_REVISIONS.add_suffix(poll_id.as_bytes());
+    while removed < limit && !revisions.is_empty(deps.storage)? {
+        revisions.pop(deps.storage)?;
+        removed += 1;
+    }
+    result.cleaned_up = removed < limit
+        || (get_turnout(deps.storage, &poll_id)? == 0
+            && FLAGS.add_suffix(poll_id.as_bytes()).get_len(deps.storage)? == 0
+            && revisions.is_empty(deps.storage)?);
+    POLL_RESULTS.insert(deps.storage, &poll_id, &result)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "archive_poll")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("removed", removed.to_string())
+        .add_attribute("cleaned_up", result.cleaned_up.to_string()))
+}
+
+// try_delete_poll deletes one of the sender's polls that never got a vote, or one of their drafts
+// The poll's flags and revisions are removed, its deposit (if any) is refunded to the sender, and it stops
+// counting towards the sender's open poll quota (the creation fee is kept)
+// Fails if:
+//    - the poll doesn't exist
+//    - the sender isn't the poll's creator
+//    - the poll has any votes
+pub fn try_delete_poll(
+    deps: DepsMut,
+    info: MessageInfo,
+    poll_id: String,
+) -> StdResult<Response> {
+    
+    // Drafts are just removed
+    let creator_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let creator = voter_nullifier(deps.as_ref(), "creator", &creator_canonical)?;
+    let drafts = DRAFTS.add_suffix(creator.as_bytes());
+    if drafts.contains(deps.storage, &poll_id) {
+        drafts.remove(deps.storage, &poll_id)?;
+        return Ok(Response::new().add_attribute("action", "delete_draft").add_attribute("poll_id", poll_id));
     }
     
+    let poll = POLLS.get(deps.storage, &poll_id)
+        .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+    check_creator(deps.as_ref(), &poll_id, &info.sender)?;
+    if has_votes(&poll) {
+        return Err(StdError::generic_err("Polls can't be deleted after the first vote"));
+    }
+    
+    // Remove the poll and everything stored for it
+    POLLS.remove(deps.storage, &poll_id)?;
+    release_content_id(deps.storage, &poll)?;
+    release_open_poll(deps.storage, &poll_id)?;
+    remove_poll_creator(deps.storage, &poll_id)?;
+    let poll_flags = FLAGS.add_suffix(poll_id.as_bytes());
+    let flag_count = poll_flags.get_len(deps.storage)?;
+    remove_keymap_entries(deps.storage, &poll_flags, flag_count)?;
+    let revisions = POLL_REVISIONS.add_suffix(poll_id.as_bytes());
+    while !revisions.is_empty(deps.storage)? {
+        revisions.pop(deps.storage)?;
+    }
+    
+    // Decrement poll count
+    let current_count = POLL_COUNT.load(deps.storage)?;
+    POLL_COUNT.save(deps.storage, &(current_count - 1))?;
+    
+    // Refund the deposit (if any)
+    let mut response = Response::new().add_attribute("action", "delete_poll").add_attribute("poll_id", poll_id.clone());
+    if let Some(deposit) = POLL_DEPOSITS.get(deps.storage, &poll_id) {
+        POLL_DEPOSITS.remove(deps.storage, &poll_id)?;
+        let mut treasury = TREASURY.load(deps.storage)?;
+        treasury.deposits_held -= deposit.amount;
+        TREASURY.save(deps.storage, &treasury)?;
+        response = response.add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin::new(deposit.amount.u128(), FEE_DENOM)],
+        });
+    }
+    
+    Ok(response)
+}
+
+// try_cast_vote allows a user meeting the poll's eligibility rules to vote on an existing poll
+// The voter is the sender, or the signer of a relayed ballot
+// It checks the voter's eligibility, prevents double voting, and updates the tally
+// (by the voter's weight on weighted polls, otherwise by 1)
+// On polls with a breakdown, the voter's group is read from their SCT and counted privately
+// The response is padded (see `execute`), and only includes the option if hide_option is false
+// The vote fails if the poll has been edited since the voter saw it (revision isn't the poll's edits),
+// as its options may have changed
+#[allow(clippy::too_many_arguments)]
+pub fn try_cast_vote(
+    deps: DepsMut,
+    env: &Env,
+    voter: Addr,
+    poll_id: String,
+    revision: u32,
+    option_idx: u32,
+    credentials: VoterCredentials,
+    hide_option: bool,
+) -> StdResult<Response> {
+    
+    let sender_canonical = deps.api.addr_canonicalize(voter.as_str())?;
+    
     // Validate the vote
     validate_vote(&poll_id, option_idx, deps.as_ref())?;
+    let mut poll = POLLS.get(deps.storage, &poll_id)
+        .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+    if !is_poll_open(&poll, env.block.time) {
+        return Err(StdError::generic_err("Poll is closed"));
+    }
+    if poll.edits != revision {
+        return Err(StdError::generic_err(format!(
+            "Poll was edited after this vote was made (vote is for revision {}, poll is at revision {})", revision, poll.edits
+        )));
+    }
     
-    // Create vote key (poll_id + hashed voter address)
-    let vote_key = format!("{}_{}", poll_id, hex::encode(sender_canonical.as_slice()));
+    // Check the voter is eligible and get their voting weight
+    let weight = get_voter_weight(deps.as_ref(), &voter, &poll, &credentials)?;
+    let group = match &poll.breakdown {
+        Some(breakdown) => Some(get_breakdown_group(deps.as_ref(), &voter, breakdown, &credentials)?),
+        None => None,
+    };
+    
+    // Create vote key (the voter's nullifier for this poll)
+    let poll_votes = VOTES.add_suffix(poll_id.as_bytes());
+    let nullifier = voter_nullifier(deps.as_ref(), &poll_id, &sender_canonical)?;
     
     // Check if user has already voted
-    if VOTES.contains(deps.storage, &vote_key) {
+    if poll_votes.contains(deps.storage, &nullifier) {
         return Err(StdError::generic_err("You have already voted on this poll"));
     }
     
-    // Record the vote
-    VOTES.insert(deps.storage, &vote_key, &option_idx)?;
+    // Record the vote, and add the poll to the voter's private list of voted polls
+    poll_votes.insert(deps.storage, &nullifier, &option_idx)?;
+    VOTED_POLLS.add_suffix(voter_nullifier(deps.as_ref(), "voted_polls", &sender_canonical)?.as_bytes())
+        .push(deps.storage, &poll_id)?;
     
     // Update the poll tally
+    // Every option is updated (by 0 for the others), so gas use doesn't depend on the option
+    for (i, count) in poll.tally.iter_mut().enumerate() {
+        let added = if i == option_idx as usize { weight } else { Uint128::zero() };
+        *count = count.checked_add(added)?;
+    }
+    POLLS.insert(deps.storage, &poll_id, &poll)?;
+    
+    // Update the breakdown counts
+    // Every cell is updated too, so gas use doesn't depend on the option or the voter's group
+    if let (Some(breakdown), Some(group)) = (&poll.breakdown, group) {
+        let mut counts = BREAKDOWN_COUNTS.get(deps.storage, &poll_id)
+            .unwrap_or_else(|| vec![vec![0; poll.options.len()]; breakdown.values.len() + 1]);
+        for (i, row) in counts.iter_mut().enumerate() {
+            for (j, count) in row.iter_mut().enumerate() {
+                *count += (i == group && j == option_idx as usize) as u32;
+            }
+        }
+        BREAKDOWN_COUNTS.insert(deps.storage, &poll_id, &counts)?;
+    }
+    
+    let response = Response::new()
+        .add_attribute("action", "cast_vote")
+        .add_attribute("poll_id", poll_id);
+    if hide_option {
+        return Ok(response);
+    }
+    Ok(response.add_attribute("option_idx", option_idx.to_string()))
+}
+
+// try_submit_ballot answers every question of a survey at once
+// The respondent's eligibility is checked once, and their ballot is their only participation record
+// Each chosen option's tally goes up by the respondent's weight (like cast_vote),
+// and each question the respondent was shown counts them in its respondents
+// The response is padded (see `execute`), and never includes the answers
+// Fails if:
+//    - the poll doesn't exist, isn't a survey, or is closed
+//    - the answers don't match the questions (see validate_answers)
+//    - the sender doesn't meet the survey's eligibility rules
+//    - the sender has already submitted a ballot for the survey
+pub fn try_submit_ballot(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    answers: Vec<Vec<u32>>,
+    credentials: VoterCredentials,
+) -> StdResult<Response> {
+    
+    let mut poll = POLLS.get(deps.storage, &poll_id)
+        .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+    if poll.questions.is_empty() {
+        return Err(StdError::generic_err("This poll isn't a survey, use CastVote to vote on it"));
+    }
+    if !is_poll_open(&poll, env.block.time) {
+        return Err(StdError::generic_err("Poll is closed"));
+    }
+    let shown = validate_answers(&poll.questions, &answers)?;
+    
+    // Check the respondent is eligible and get their voting weight
+    let weight = get_voter_weight(deps.as_ref(), &info.sender, &poll, &credentials)?;
+    
+    // Check the respondent hasn't already submitted a ballot
+    let sender_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let poll_ballots = SURVEY_BALLOTS.add_suffix(poll_id.as_bytes());
+    let nullifier = voter_nullifier(deps.as_ref(), &poll_id, &sender_canonical)?;
+    if poll_ballots.contains(deps.storage, &nullifier) {
+        return Err(StdError::generic_err("You have already submitted a ballot for this survey"));
+    }
+    
+    // Update each question's tally and respondents
+    // Every option is updated (by 0 for the others), so gas use doesn't depend on the answers
+    for ((question, chosen), shown) in poll.questions.iter_mut().zip(&answers).zip(shown) {
+        for (i, count) in question.tally.iter_mut().enumerate() {
+            let added = if chosen.contains(&(i as u32)) { weight } else { Uint128::zero() };
+            *count = count.checked_add(added)?;
+        }
+        question.respondents += shown as u32;
+    }
+    POLLS.insert(deps.storage, &poll_id, &poll)?;
+    
+    // Update the cross-tab counts (every cell, so gas use doesn't depend on the answers either)
+    if !poll.cross_tabs.is_empty() {
+        let mut cross_tab_counts = CROSS_TAB_COUNTS.get(deps.storage, &poll_id)
+            .unwrap_or_else(|| new_cross_tab_counts(&poll));
+        for (cross_tab, tab_counts) in poll.cross_tabs.iter().zip(cross_tab_counts.iter_mut()) {
+            let row_answer = &answers[cross_tab.row_question as usize];
+            let column_answer = &answers[cross_tab.column_question as usize];
+            tab_counts.respondents += (!row_answer.is_empty() && !column_answer.is_empty()) as u32;
+            for (row, counts) in tab_counts.counts.iter_mut().enumerate() {
+                for (column, count) in counts.iter_mut().enumerate() {
+                    *count += (row_answer.contains(&(row as u32)) && column_answer.contains(&(column as u32))) as u32;
+                }
+            }
+        }
+        CROSS_TAB_COUNTS.insert(deps.storage, &poll_id, &cross_tab_counts)?;
+    }
+    
+    // Record the ballot, and add the survey to the respondent's private list of voted polls
+    poll_ballots.insert(deps.storage, &nullifier, &answers)?;
+    VOTED_POLLS.add_suffix(voter_nullifier(deps.as_ref(), "voted_polls", &sender_canonical)?.as_bytes())
+        .push(deps.storage, &poll_id)?;
+    
+    Ok(Response::new().add_attribute("action", "submit_ballot").add_attribute("poll_id", poll_id))
+}
+
+// try_relay_vote casts a vote from a ballot the voter signed off-chain
+// The ballot's signature identifies the voter, so the relayer can't vote on anyone else's behalf
+// and double voting is checked against the signer rather than the sender
+// Fails if:
+//    - the ballot is invalid (see validate_ballot)
+//    - the poll doesn't exist, or was created at a different time than the ballot says
+//    - any reason cast_vote would fail for the voter
+pub fn try_relay_vote(
+    deps: DepsMut,
+    env: Env,
+    ballot: Permit<Ballot>,
+    credentials: VoterCredentials,
+    hide_option: bool,
+) -> StdResult<Response> {
+    
+    // Verify the ballot and get the voter's address from its signature
+    let voter = validate_ballot(deps.as_ref(), &env, &ballot)?;
+    let vote = &ballot.params.permissions[0];
+    
+    // Check the ballot was signed for this poll, not an earlier one with the same poll_id
+    let poll = POLLS.get(deps.storage, &vote.poll_id)
+        .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+    if poll.created_at != vote.created_at {
+        return Err(StdError::generic_err("Ballot was signed for an earlier poll with this poll_id"));
+    }
+    
+    // A ballot submitted again is caught by the double voting check, like any second vote
+    try_cast_vote(deps, &env, voter, vote.poll_id.clone(), vote.revision, vote.option_idx, credentials, hide_option)
+}
+
+// try_flag_poll flags a poll for moderation
+// Flags are keyed by the flagger's nullifier like votes, so flaggers stay anonymous
+// The response is padded like cast_vote
+// Fails if:
+//    - the poll doesn't exist
+//    - the reason code is more than MAX_FLAG_REASON
+//    - the sender doesn't meet the poll's eligibility rules
+//    - the sender has already flagged the poll
+pub fn try_flag_poll(
+    deps: DepsMut,
+    info: MessageInfo,
+    poll_id: String,
+    reason_code: u8,
+    credentials: VoterCredentials,
+) -> StdResult<Response> {
+    
+    let mut poll = POLLS.get(deps.storage, &poll_id)
+        .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+    if reason_code > MAX_FLAG_REASON {
+        return Err(StdError::generic_err(format!("Invalid reason code (max {})", MAX_FLAG_REASON)));
+    }
+    
+    // Check the flagger is eligible to vote on the poll
+    check_voter_eligibility(deps.as_ref(), &info.sender, &poll, &credentials)?;
+    
+    // Check the flagger hasn't already flagged the poll
+    let sender_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let nullifier = voter_nullifier(deps.as_ref(), &format!("flag_{}", poll_id), &sender_canonical)?;
+    let poll_flags = FLAGS.add_suffix(poll_id.as_bytes());
+    if poll_flags.contains(deps.storage, &nullifier) {
+        return Err(StdError::generic_err("You have already flagged this poll"));
+    }
+    
+    // Record the flag
+    poll_flags.insert(deps.storage, &nullifier, &reason_code)?;
+    poll.flags += 1;
+    POLLS.insert(deps.storage, &poll_id, &poll)?;
+    
+    Ok(Response::new().add_attribute("action", "flag_poll").add_attribute("poll_id", poll_id))
+}
+
+// try_moderate_poll hides or restores a poll, and records the action in the audit log
+// Hiding a poll also forfeits its deposit (if one is held), and stops it counting towards its creator's open poll quota
+// Restoring a poll doesn't give its deposit back
+// Fails if:
+//    - the sender isn't a moderator or the admin
+//    - the poll doesn't exist
+//    - the reason is empty or longer than 200 characters
+pub fn try_moderate_poll(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    action: ModerationAction,
+    reason: String,
+) -> StdResult<Response> {
+    
+    check_moderator(deps.as_ref(), &info.sender)?;
     let mut poll = POLLS.get(deps.storage, &poll_id)
         .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
-    poll.tally[option_idx as usize] += 1;
+    if reason.trim().is_empty() || reason.len() > 200 {
+        return Err(StdError::generic_err("A reason is required (max 200 chars)"));
+    }
+    
+    // Update the poll
+    poll.moderation = Some(action.clone());
     POLLS.insert(deps.storage, &poll_id, &poll)?;
+    if action == ModerationAction::Hide {
+        forfeit_deposit(deps.branch(), &poll_id)?;
+        release_open_poll(deps.storage, &poll_id)?;
+    }
+    
+    // Record the action
+    let entry = AuditEntry {
+        timestamp: env.block.time,
+        moderator: info.sender,
+        action,
+        poll_id: poll_id.clone(),
+        reason,
+    };
+    AUDIT_LOG.push(deps.storage, &entry)?;
+    
+    Ok(Response::new().add_attribute("action", "moderate_poll").add_attribute("poll_id", poll_id))
+}
+
+// try_set_moderators replaces the list of moderators
+// Fails if:
+//    - the sender isn't the admin
+//    - there are more than MAX_MODERATORS moderators
+pub fn try_set_moderators(
+    deps: DepsMut,
+    info: MessageInfo,
+    moderators: Vec<Addr>,
+) -> StdResult<Response> {
+    
+    check_admin(deps.as_ref(), &info.sender)?;
+    if moderators.len() > MAX_MODERATORS {
+        return Err(StdError::generic_err(format!("Too many moderators (max {})", MAX_MODERATORS)));
+    }
+    
+    let moderators = moderators
+        .iter()
+        .map(|moderator| deps.api.addr_canonicalize(moderator.as_str()))
+        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+    MODERATORS.save(deps.storage, &moderators)?;
+    
+    Ok(Response::new().add_attribute("action", "set_moderators"))
+}
+
+// try_set_organiser registers the organiser name for the given addresses (replacing any previous ones)
+// An empty list of addresses removes the organiser
+// Fails if:
+//    - the sender isn't the admin
+//    - the name is empty or longer than 50 characters
+//    - there are more than MAX_ORGANISER_ADDRESSES addresses
+pub fn try_set_organiser(
+    deps: DepsMut,
+    info: MessageInfo,
+    name: String,
+    addresses: Vec<Addr>,
+) -> StdResult<Response> {
+    
+    check_admin(deps.as_ref(), &info.sender)?;
+    
+    // Validate the organiser
+    if name.trim().is_empty() || name.len() > 50 {
+        return Err(StdError::generic_err("Organiser name must be between 1 and 50 characters"));
+    }
+    if addresses.len() > MAX_ORGANISER_ADDRESSES {
+        return Err(StdError::generic_err(format!("Too many organiser addresses (max {})", MAX_ORGANISER_ADDRESSES)));
+    }
+    
+    // Save (or remove) the organiser
+    if addresses.is_empty() {
+        ORGANISERS.remove(deps.storage, &name)?;
+    } else {
+        let addresses = addresses
+            .iter()
+            .map(|address| deps.api.addr_canonicalize(address.as_str()))
+            .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+        ORGANISERS.insert(deps.storage, &name, &addresses)?;
+    }
+    
+    Ok(Response::new().add_attribute("action", "set_organiser").add_attribute("name", name))
+}
+
+// try_set_config changes the contract config, leaving unset fields unchanged
+// Changes to the deposit settings only apply to polls created afterwards
+// Fails if:
+//    - the sender isn't the admin
+#[allow(clippy::too_many_arguments)]
+pub fn try_set_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    require_sct_to_create: Option<bool>,
+    creation_fee: Option<Uint128>,
+    creation_deposit: Option<Uint128>,
+    deposit_refund_turnout: Option<u32>,
+    max_polls_per_window: Option<u32>,
+    rate_limit_window: Option<u64>,
+    max_open_polls: Option<u32>,
+    flag_threshold: Option<u32>,
+) -> StdResult<Response> {
+    
+    check_admin(deps.as_ref(), &info.sender)?;
+    
+    let mut config = CONFIG.load(deps.storage)?;
+    if let Some(require_sct_to_create) = require_sct_to_create {
+        config.require_sct_to_create = require_sct_to_create;
+    }
+    if let Some(creation_fee) = creation_fee {
+        config.creation_fee = creation_fee;
+    }
+    if let Some(creation_deposit) = creation_deposit {
+        config.creation_deposit = creation_deposit;
+    }
+    if let Some(deposit_refund_turnout) = deposit_refund_turnout {
+        config.deposit_refund_turnout = deposit_refund_turnout;
+    }
+    if let Some(max_polls_per_window) = max_polls_per_window {
+        config.max_polls_per_window = max_polls_per_window;
+    }
+    if let Some(rate_limit_window) = rate_limit_window {
+        config.rate_limit_window = rate_limit_window;
+    }
+    if let Some(max_open_polls) = max_open_polls {
+        config.max_open_polls = max_open_polls;
+    }
+    if let Some(flag_threshold) = flag_threshold {
+        config.flag_threshold = flag_threshold;
+    }
+    validate_rate_limit(&config)?;
+    CONFIG.save(deps.storage, &config)?;
+    
+    Ok(Response::new().add_attribute("action", "set_config"))
+}
+
+// try_set_community_config registers a community (SNIP-721 contract) and overrides the config for it
+// Only registered communities are used for creator credential checks (see `get_poll_communities`)
+// None removes the community, so its polls follow the contract config again
+// Fails if:
+//    - the sender isn't the admin
+//    - the code hash is empty
+pub fn try_set_community_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    community: Addr,
+    code_hash: String,
+    require_sct_to_create: Option<bool>,
+) -> StdResult<Response> {
+    
+    check_admin(deps.as_ref(), &info.sender)?;
+    
+    let community = deps.api.addr_validate(community.as_str())?;
+    match require_sct_to_create {
+        Some(require_sct_to_create) => {
+            if code_hash.is_empty() {
+                return Err(StdError::generic_err("A community's code hash can't be empty"));
+            }
+            let rule = CommunityRule { code_hash, require_sct_to_create };
+            COMMUNITY_CREATION_RULES.insert(deps.storage, &community.to_string(), &rule)?;
+        }
+        None => {
+            if COMMUNITY_CREATION_RULES.contains(deps.storage, &community.to_string()) {
+                COMMUNITY_CREATION_RULES.remove(deps.storage, &community.to_string())?;
+            }
+        }
+    }
+    
+    Ok(Response::new().add_attribute("action", "set_community_config").add_attribute("community", community))
+}
+
+// try_claim_deposit returns a poll's deposit to its depositor once the poll reaches the refund turnout
+// Fails if:
+//    - the poll has no deposit held (none was paid, or it was already claimed or forfeited)
+//    - the sender isn't the depositor
+//    - the poll has fewer votes than the refund turnout
+pub fn try_claim_deposit(
+    deps: DepsMut,
+    info: MessageInfo,
+    poll_id: String,
+) -> StdResult<Response> {
+    
+    let deposit = POLL_DEPOSITS.get(deps.storage, &poll_id)
+        .ok_or_else(|| StdError::generic_err("No deposit is held for this poll"))?;
+    let sender_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if voter_nullifier(deps.as_ref(), "creator", &sender_canonical)? != deposit.depositor {
+        return Err(StdError::generic_err("Only the poll's depositor can claim its deposit"));
+    }
+    let turnout = match POLL_RESULTS.get(deps.storage, &poll_id) {
+        Some(result) => result.voters,
+        None => get_turnout(deps.storage, &poll_id)?,
+    };
+    if turnout < deposit.refund_turnout {
+        return Err(StdError::generic_err(format!(
+            "Poll has {} votes, it needs {} to refund the deposit", turnout, deposit.refund_turnout
+        )));
+    }
+    
+    // Release the deposit
+    POLL_DEPOSITS.remove(deps.storage, &poll_id)?;
+    let mut treasury = TREASURY.load(deps.storage)?;
+    treasury.deposits_held -= deposit.amount;
+    TREASURY.save(deps.storage, &treasury)?;
     
     Ok(Response::new()
-        .add_attribute("action", "cast_vote")
-        .add_attribute("poll_id", poll_id)
-        .add_attribute("option_idx", option_idx.to_string()))
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin::new(deposit.amount.u128(), FEE_DENOM)],
+        })
+        .add_attribute("action", "claim_deposit"))
+}
+
+// try_withdraw_fees sends collected fees (and forfeited deposits) to the recipient
+// Held deposits can't be withdrawn
+// Fails if:
+//    - the sender isn't the admin
+//    - the amount is zero or more than the collected fees
+pub fn try_withdraw_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    amount: Option<Uint128>,
+    recipient: Option<Addr>,
+) -> StdResult<Response> {
+    
+    check_admin(deps.as_ref(), &info.sender)?;
+    
+    let mut treasury = TREASURY.load(deps.storage)?;
+    let amount = amount.unwrap_or(treasury.fees);
+    if amount.is_zero() || amount > treasury.fees {
+        return Err(StdError::generic_err(format!("Invalid amount (the treasury holds {} uscrt in fees)", treasury.fees)));
+    }
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(recipient.as_str())?,
+        None => info.sender,
+    };
+    treasury.fees -= amount;
+    TREASURY.save(deps.storage, &treasury)?;
+    
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin::new(amount.u128(), FEE_DENOM)],
+        })
+        .add_attribute("action", "withdraw_fees"))
+}
+
+// try_change_admin hands the admin role to another address
+// Fails if:
+//    - the sender isn't the admin
+pub fn try_change_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    admin: Addr,
+) -> StdResult<Response> {
+    
+    check_admin(deps.as_ref(), &info.sender)?;
+    
+    ADMIN.save(deps.storage, &deps.api.addr_canonicalize(admin.as_str())?)?;
+    
+    Ok(Response::new().add_attribute("action", "change_admin"))
+}
+
+// try_revoke_permit revokes one of the sender's permits by name
+// Revoked permits (and ballots) with that name can no longer be used with this contract
+pub fn try_revoke_permit(
+    deps: DepsMut,
+    info: MessageInfo,
+    permit_name: String,
+) -> StdResult<Response> {
+    
+    RevokedPermits::revoke_permit(deps.storage, PREFIX_REVOKED_PERMITS, info.sender.as_str(), &permit_name);
+    
+    Ok(Response::new().add_attribute("action", "revoke_permit"))
+}
+
+// try_create_viewing_key creates a random viewing key for the sender and returns it in the response data
+// Only a hash of the key is stored
+pub fn try_create_viewing_key(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    entropy: String,
+) -> StdResult<Response> {
+    
+    let key = ViewingKey::create(deps.storage, &info, &env, info.sender.as_str(), entropy.as_bytes());
+    
+    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::CreateViewingKey { key })?))
+}
+
+// try_set_viewing_key sets the sender's viewing key to the given key
+// Only a hash of the key is stored
+pub fn try_set_viewing_key(
+    deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+) -> StdResult<Response> {
+    
+    ViewingKey::set(deps.storage, info.sender.as_str(), &key);
+    
+    Ok(Response::new().add_attribute("action", "set_viewing_key"))
 }
 
 
@@ -178,13 +1320,53 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         // Get all polls
         // (anyone)
-        QueryMsg::GetPolls {} => query_get_polls(deps),
+        QueryMsg::GetPolls { include_hidden } => query_get_polls(deps, include_hidden.unwrap_or(false)),
+        // Get a poll by poll_id
+        // (anyone)
+        QueryMsg::GetPoll { poll_id } => query_get_poll(deps, poll_id),
+        // Get a cross-tab of two questions in a closed survey
+        // (anyone)
+        QueryMsg::GetCrossTab { poll_id, row_question, column_question } => {
+            query_get_cross_tab(deps, env, poll_id, row_question, column_question)
+        }
+        // Get a closed poll's breakdown
+        // (anyone)
+        QueryMsg::GetBreakdown { poll_id } => query_get_breakdown(deps, env, poll_id),
+        // Get an archived poll's final result
+        // (anyone)
+        QueryMsg::GetPollResult { poll_id } => query_get_poll_result(deps, poll_id),
+        // Get a poll's previous contents
+        // (anyone)
+        QueryMsg::GetPollRevisions { poll_id } => query_get_poll_revisions(deps, poll_id),
+        // Get a poll's flags by reason
+        // (anyone)
+        QueryMsg::GetPollFlags { poll_id } => query_get_poll_flags(deps, poll_id),
         // Get number of polls
         // (anyone)
         QueryMsg::GetNumPolls {} => query_get_num_polls(deps),
+        // Get the verified organisers
+        // (anyone)
+        QueryMsg::GetOrganisers {} => query_get_organisers(deps),
+        // Get the config
+        // (anyone)
+        QueryMsg::GetConfig {} => query_get_config(deps),
+        // Get the treasury
+        // (anyone)
+        QueryMsg::GetTreasury {} => query_get_treasury(deps),
+        // Get the moderators
+        // (anyone)
+        QueryMsg::GetModerators {} => query_get_moderators(deps),
+        // Get the moderation audit log
+        // (anyone)
+        QueryMsg::GetAuditLog { page, page_size } => {
+            query_get_audit_log(deps, page.unwrap_or(0), page_size.unwrap_or(MAX_AUDIT_ENTRIES_PER_QUERY))
+        }
         // Handle permit-based queries
         // (anyone, but requires permit)
         QueryMsg::WithPermit { permit, query } => permit_queries(deps, env, permit, query),
+        // Handle viewing-key-based queries
+        // (anyone, but requires viewing key)
+        QueryMsg::WithViewingKey { address, key, query } => viewing_key_queries(deps, address, key, query),
     }
 }
 
@@ -192,12 +1374,12 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
 fn permit_queries(
     deps: Deps,
     env: Env,
-    permit: Permit,
+    permit: Permit<PollPermissions>,
     query: QueryWithPermit,
 ) -> StdResult<Binary> {
     let account = secret_toolkit::permit::validate(
         deps,
-        "revoked_permits",
+        PREFIX_REVOKED_PERMITS,
         &permit,
         env.contract.address.to_string(),
         None,
@@ -208,36 +1390,300 @@ fn permit_queries(
     // Canonicalize the account address
     let canonical_account = deps.api.addr_canonicalize(account.as_str())?;
 
-    // Process query
+    // Check the permit grants the query's permission
+    let permission = match &query {
+        QueryWithPermit::GetMyVote { .. }
+        | QueryWithPermit::GetMyBallot { .. }
+        | QueryWithPermit::GetMyVotes { .. }
+        | QueryWithPermit::GetMyVotedPolls { .. } => PollPermissions::Votes,
+        QueryWithPermit::GetMyPolls { .. }
+        | QueryWithPermit::GetMyDrafts { .. } => PollPermissions::Polls,
+    };
+    check_permission(&permit, permission)?;
+
+    authenticated_queries(deps, &canonical_account, query)
+}
+
+// Handle viewing-key-based queries
+// A viewing key grants every permission
+fn viewing_key_queries(
+    deps: Deps,
+    address: Addr,
+    key: String,
+    query: QueryWithPermit,
+) -> StdResult<Binary> {
+    ViewingKey::check(deps.storage, address.as_str(), &key)
+        .map_err(|_| StdError::generic_err("Wrong viewing key for this address or viewing key not set"))?;
+
+    // (viewing key validated!)
+
+    // Canonicalize the account address
+    let canonical_account = deps.api.addr_canonicalize(address.as_str())?;
+
+    authenticated_queries(deps, &canonical_account, query)
+}
+
+// Process a query for an account authenticated by permit or viewing key
+fn authenticated_queries(
+    deps: Deps,
+    account: &CanonicalAddr,
+    query: QueryWithPermit,
+) -> StdResult<Binary> {
     match query {
-        QueryWithPermit::GetMyVote { poll_id } => query_get_my_vote(deps, &canonical_account, &poll_id)
+        QueryWithPermit::GetMyVote { poll_id } => query_get_my_vote(deps, account, &poll_id),
+        QueryWithPermit::GetMyBallot { poll_id } => query_get_my_ballot(deps, account, &poll_id),
+        QueryWithPermit::GetMyVotes { poll_ids } => query_get_my_votes(deps, account, poll_ids),
+        QueryWithPermit::GetMyVotedPolls { page, page_size } => {
+            query_get_my_voted_polls(deps, account, page.unwrap_or(0), page_size.unwrap_or(MAX_VOTES_PER_QUERY))
+        }
+        QueryWithPermit::GetMyPolls { page, page_size } => {
+            query_get_my_polls(deps, account, page.unwrap_or(0), page_size.unwrap_or(MAX_POLLS_PER_QUERY))
+        }
+        QueryWithPermit::GetMyDrafts {} => query_get_my_drafts(deps, account),
+    }
+}
+
+// Query all polls (hidden polls are left out unless include_hidden is set)
+fn query_get_polls(
+    deps: Deps,
+    include_hidden: bool,
+) -> StdResult<Binary> {
+    
+    let config = CONFIG.load(deps.storage)?;
+    
+    // Check if iterator is available, if not return empty vec
+    let polls: Vec<Poll> = match POLLS.iter(deps.storage) {
+        Ok(iter) => iter
+            .filter_map(|item| item.ok().map(|(_, poll)| poll))
+            .filter(|poll| include_hidden || !is_poll_hidden(poll, &config))
+            .collect(),
+        Err(_) => Vec::new(), // Return empty vector if iterator fails
+    };
+    
+    to_binary(&QueryAnswer::GetPolls { polls })
+}
+
+// Query a single poll by poll_id, whether or not it's hidden
+fn query_get_poll(
+    deps: Deps,
+    poll_id: String,
+) -> StdResult<Binary> {
+    
+    let poll = POLLS.get(deps.storage, &poll_id)
+        .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+    let hidden = is_poll_hidden(&poll, &CONFIG.load(deps.storage)?);
+    
+    to_binary(&QueryAnswer::GetPoll { poll, hidden })
+}
+
+// Query a closed survey's answers to one question broken down by the answers to another
+// The pair must be one of the survey's cross_tabs, whose counts are kept up to date as ballots are submitted
+// Open surveys are refused, as comparing cross-tabs before and after someone submits would reveal their answers
+// Archived surveys answer from the cross-tabs kept in their final result
+// A multiple choice answer counts towards every cell it covers
+fn query_get_cross_tab(
+    deps: Deps,
+    env: Env,
+    poll_id: String,
+    row_question: u32,
+    column_question: u32,
+) -> StdResult<Binary> {
+    
+    let poll = match POLLS.get(deps.storage, &poll_id) {
+        Some(poll) => poll,
+        None => {
+            let result = POLL_RESULTS.get(deps.storage, &poll_id)
+                .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+            let cross_tab = result.cross_tabs.into_iter()
+                .find(|cross_tab| cross_tab.row_question == row_question && cross_tab.column_question == column_question)
+                .ok_or_else(|| StdError::generic_err("This pair of questions isn't one of the survey's cross-tabs, or had too few respondents"))?;
+            return to_binary(&QueryAnswer::GetCrossTab { respondents: cross_tab.respondents, cells: cross_tab.cells });
+        }
+    };
+    if poll.questions.is_empty() {
+        return Err(StdError::generic_err("Cross-tabs are only available for surveys"));
+    }
+    if is_poll_open(&poll, env.block.time) {
+        return Err(StdError::generic_err("Cross-tabs are only available once the survey has closed"));
+    }
+    let position = poll.cross_tabs.iter()
+        .position(|cross_tab| cross_tab.row_question == row_question && cross_tab.column_question == column_question)
+        .ok_or_else(|| StdError::generic_err("This pair of questions isn't one of the survey's cross-tabs"))?;
+    
+    let tab_counts = CROSS_TAB_COUNTS.get(deps.storage, &poll_id)
+        .unwrap_or_else(|| new_cross_tab_counts(&poll))
+        .swap_remove(position);
+    let (respondents, cells) = get_cross_tab_cells(tab_counts)?;
+    
+    to_binary(&QueryAnswer::GetCrossTab { respondents, cells })
+}
+
+// Query a closed poll's votes broken down by its breakdown trait
+// Open polls are refused for the same reason as cross-tabs
+// Archived polls answer from the breakdown kept in their final result
+fn query_get_breakdown(
+    deps: Deps,
+    env: Env,
+    poll_id: String,
+) -> StdResult<Binary> {
+    
+    let poll = match POLLS.get(deps.storage, &poll_id) {
+        Some(poll) => poll,
+        None => {
+            let result = POLL_RESULTS.get(deps.storage, &poll_id)
+                .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+            let cells = result.breakdown
+                .ok_or_else(|| StdError::generic_err("This poll has no breakdown, or had too few voters for one"))?;
+            return to_binary(&QueryAnswer::GetBreakdown { voters: result.voters, cells });
+        }
+    };
+    if poll.breakdown.is_none() {
+        return Err(StdError::generic_err("This poll has no breakdown"));
+    }
+    if is_poll_open(&poll, env.block.time) {
+        return Err(StdError::generic_err("Breakdowns are only available once the poll has closed"));
+    }
+    let (voters, cells) = get_breakdown_cells(deps.storage, &poll)?;
+    
+    to_binary(&QueryAnswer::GetBreakdown { voters, cells })
+}
+
+// Query the final result of an archived poll
+fn query_get_poll_result(
+    deps: Deps,
+    poll_id: String,
+) -> StdResult<Binary> {
+    
+    let result = POLL_RESULTS.get(deps.storage, &poll_id)
+        .ok_or_else(|| StdError::generic_err("No archived result for this poll"))?;
+    
+    to_binary(&QueryAnswer::GetPollResult { result })
+}
+
+// Query the previous contents of a poll (oldest first)
+fn query_get_poll_revisions(
+    deps: Deps,
+    poll_id: String,
+) -> StdResult<Binary> {
+    
+    if !POLLS.contains(deps.storage, &poll_id) {
+        return Err(StdError::generic_err("Poll does not exist"));
+    }
+    let revisions = POLL_REVISIONS.add_suffix(poll_id.as_bytes())
+        .iter(deps.storage)?
+        .collect::<StdResult<Vec<PollRevision>>>()?;
+    
+    to_binary(&QueryAnswer::GetPollRevisions { revisions })
+}
+
+// Query the number of flags for each reason code on a poll
+fn query_get_poll_flags(
+    deps: Deps,
+    poll_id: String,
+) -> StdResult<Binary> {
+    
+    if !POLLS.contains(deps.storage, &poll_id) {
+        return Err(StdError::generic_err("Poll does not exist"));
     }
+    let mut reasons = vec![0u32; MAX_FLAG_REASON as usize + 1];
+    for item in FLAGS.add_suffix(poll_id.as_bytes()).iter(deps.storage)? {
+        let (_, reason_code) = item?;
+        reasons[reason_code as usize] += 1;
+    }
+    
+    to_binary(&QueryAnswer::GetPollFlags { reasons })
+}
+
+// Query number of polls
+fn query_get_num_polls(
+    deps: Deps
+) -> StdResult<Binary> {
+    
+    let num_polls = POLL_COUNT.load(deps.storage)?;
+    
+    to_binary(&QueryAnswer::GetNumPolls { num_polls })
+}
+
+// Query the verified organisers and their addresses
+fn query_get_organisers(
+    deps: Deps
+) -> StdResult<Binary> {
+    
+    let organisers = ORGANISERS
+        .iter(deps.storage)?
+        .map(|item| {
+            let (name, addresses) = item?;
+            let addresses = addresses
+                .iter()
+                .map(|address| deps.api.addr_humanize(address))
+                .collect::<StdResult<Vec<Addr>>>()?;
+            Ok(Organiser { name, addresses })
+        })
+        .collect::<StdResult<Vec<Organiser>>>()?;
+    
+    to_binary(&QueryAnswer::GetOrganisers { organisers })
+}
+
+// Query the contract config and its per-community overrides
+fn query_get_config(
+    deps: Deps
+) -> StdResult<Binary> {
+    
+    let config = CONFIG.load(deps.storage)?;
+    let communities = COMMUNITY_CREATION_RULES
+        .iter(deps.storage)?
+        .map(|item| {
+            let (community, rule) = item?;
+            Ok(CommunityConfig {
+                community: Addr::unchecked(community),
+                code_hash: rule.code_hash,
+                require_sct_to_create: rule.require_sct_to_create,
+            })
+        })
+        .collect::<StdResult<Vec<CommunityConfig>>>()?;
+    
+    to_binary(&QueryAnswer::GetConfig { config, communities })
 }
 
-// Query all polls  
-fn query_get_polls(
+// Query the fees and deposits held by the contract
+fn query_get_treasury(
     deps: Deps
 ) -> StdResult<Binary> {
     
-    // Check if iterator is available, if not return empty vec
-    let polls: Vec<Poll> = match POLLS.iter(deps.storage) {
-        Ok(iter) => iter
-            .filter_map(|item| item.ok().map(|(_, poll)| poll))
-            .collect(),
-        Err(_) => Vec::new(), // Return empty vector if iterator fails
-    };
+    let treasury = TREASURY.load(deps.storage)?;
     
-    to_binary(&QueryAnswer::GetPolls { polls })
+    to_binary(&QueryAnswer::GetTreasury { treasury })
 }
 
-// Query number of polls
-fn query_get_num_polls(
+// Query the moderators
+fn query_get_moderators(
     deps: Deps
 ) -> StdResult<Binary> {
     
-    let num_polls = POLL_COUNT.load(deps.storage)?;
+    let moderators = MODERATORS
+        .load(deps.storage)?
+        .iter()
+        .map(|moderator| deps.api.addr_humanize(moderator))
+        .collect::<StdResult<Vec<Addr>>>()?;
     
-    to_binary(&QueryAnswer::GetNumPolls { num_polls })
+    to_binary(&QueryAnswer::GetModerators { moderators })
+}
+
+// Query a page of the moderation audit log (oldest first)
+fn query_get_audit_log(
+    deps: Deps,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    
+    if page_size == 0 || page_size > MAX_AUDIT_ENTRIES_PER_QUERY {
+        return Err(StdError::generic_err("Invalid page size (min 1, max 50)"));
+    }
+    
+    let total = AUDIT_LOG.get_len(deps.storage)?;
+    let entries = AUDIT_LOG.paging(deps.storage, page, page_size)?;
+    
+    to_binary(&QueryAnswer::GetAuditLog { entries, total })
 }
 
 // Query user's vote on a specific poll (requires permit authentication)
@@ -252,6 +1698,95 @@ fn query_get_my_vote(
     to_binary(&QueryAnswer::GetMyVote { vote })
 }
 
+// Query user's ballot on a survey (requires authentication)
+fn query_get_my_ballot(
+    deps: Deps,
+    requesting_account: &CanonicalAddr,
+    poll_id: &str,
+) -> StdResult<Binary> {
+    
+    let nullifier = voter_nullifier(deps, poll_id, requesting_account)?;
+    let answers = SURVEY_BALLOTS.add_suffix(poll_id.as_bytes()).get(deps.storage, &nullifier);
+    
+    to_binary(&QueryAnswer::GetMyBallot { answers })
+}
+
+// Query user's votes on several polls (requires authentication)
+fn query_get_my_votes(
+    deps: Deps,
+    requesting_account: &CanonicalAddr,
+    poll_ids: Vec<String>,
+) -> StdResult<Binary> {
+    
+    if poll_ids.len() > MAX_VOTES_PER_QUERY as usize {
+        return Err(StdError::generic_err("Too many poll_ids (max 50)"));
+    }
+    
+    let votes = poll_ids.into_iter()
+        .map(|poll_id| get_user_poll_vote(deps, poll_id, requesting_account))
+        .collect::<StdResult<Vec<PollVote>>>()?;
+    
+    to_binary(&QueryAnswer::GetMyVotes { votes })
+}
+
+// Query a page of the polls the user has voted on, with their votes (requires authentication)
+fn query_get_my_voted_polls(
+    deps: Deps,
+    requesting_account: &CanonicalAddr,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    
+    if page_size == 0 || page_size > MAX_VOTES_PER_QUERY {
+        return Err(StdError::generic_err("Invalid page size (min 1, max 50)"));
+    }
+    
+    let voted_polls = VOTED_POLLS.add_suffix(voter_nullifier(deps, "voted_polls", requesting_account)?.as_bytes());
+    let total = voted_polls.get_len(deps.storage)?;
+    let votes = voted_polls.paging(deps.storage, page, page_size)?
+        .into_iter()
+        .map(|poll_id| get_user_poll_vote(deps, poll_id, requesting_account))
+        .collect::<StdResult<Vec<PollVote>>>()?;
+    
+    to_binary(&QueryAnswer::GetMyVotedPolls { votes, total })
+}
+
+// Query a page of the polls the user created (requires authentication)
+fn query_get_my_polls(
+    deps: Deps,
+    requesting_account: &CanonicalAddr,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    
+    if page_size == 0 || page_size > MAX_POLLS_PER_QUERY {
+        return Err(StdError::generic_err("Invalid page size (min 1, max 20)"));
+    }
+    
+    let created_polls = CREATED_POLLS.add_suffix(voter_nullifier(deps, "creator", requesting_account)?.as_bytes());
+    let total = created_polls.get_len(deps.storage)?;
+    let polls = created_polls.paging(deps.storage, page, page_size)?
+        .into_iter()
+        .filter_map(|poll_id| POLLS.get(deps.storage, &poll_id))
+        .collect();
+    
+    to_binary(&QueryAnswer::GetMyPolls { polls, total })
+}
+
+// Query the user's unpublished drafts (requires permit or viewing key authentication)
+fn query_get_my_drafts(
+    deps: Deps,
+    requesting_account: &CanonicalAddr,
+) -> StdResult<Binary> {
+    
+    let drafts = DRAFTS.add_suffix(voter_nullifier(deps, "creator", requesting_account)?.as_bytes())
+        .iter(deps.storage)?
+        .map(|item| item.map(|(_, draft)| draft))
+        .collect::<StdResult<Vec<PollDraft>>>()?;
+    
+    to_binary(&QueryAnswer::GetMyDrafts { drafts })
+}
+
 
 
 
@@ -275,97 +1810,106 @@ fn generate_poll_id(title: &str, options: &[String]) -> String {
     hex::encode(result)
 }
 
-// SNIP-721 query structures
-#[derive(Serialize, Deserialize)]
-struct TokensQuery {
-    tokens: TokensQueryParams,
-}
-
-#[derive(Serialize, Deserialize)]
-struct TokensQueryParams {
-    owner: String,
-    viewing_key: String,
-    limit: Option<u32>,
-}
 
-#[derive(Serialize, Deserialize)]
-struct TokensResponse {
-    token_list: TokenList,
+// Helper function to generate a poll's content ID, which is its poll_id until it's edited
+// Surveys hash the title with every question and its options
+fn generate_content_id(title: &str, options: &[String], questions: &[SurveyQuestion]) -> String {
+    if questions.is_empty() {
+        return generate_poll_id(title, options);
+    }
+    let contents: Vec<String> = questions
+        .iter()
+        .flat_map(|question| std::iter::once(&question.question).chain(&question.options).cloned())
+        .collect();
+    generate_poll_id(title, &contents)
 }
 
-#[derive(Serialize, Deserialize)]
-struct TokenList {
-    tokens: Vec<String>,
+// Helper function to check no live poll has the given content ID, as its poll_id or its edited content
+// Archived polls keep their poll_id, so a new poll can't pick up their leftover votes, deposit or creator
+fn check_content_available(deps: Deps, content_id: &str) -> StdResult<()> {
+    if POLLS.contains(deps.storage, &content_id.to_string())
+        || EDITED_CONTENT.contains(deps.storage, &content_id.to_string())
+        || POLL_RESULTS.contains(deps.storage, &content_id.to_string())
+    {
+        return Err(StdError::generic_err("Poll ID already taken (same title and options)"));
+    }
+    Ok(())
 }
 
-// Helper function to check if an SCT is owned by the given address
-fn address_owns_sct(
-    deps: Deps,
-    address: &CanonicalAddr,
-    viewing_key: &str,
-) -> StdResult<bool> {
-    let sct_contract_address = SCT_CONTRACT_ADDRESS.load(deps.storage)?;
-    let sct_code_hash = SCT_CODE_HASH.load(deps.storage)?;
-    
-    // Convert canonical address back to human readable format
-    let human_address = deps.api.addr_humanize(address)?;
-    
-    // Create the proper SNIP-721 tokens query
-    let query_msg = TokensQuery {
-        tokens: TokensQueryParams {
-            owner: human_address.to_string(),
-            viewing_key: viewing_key.to_string(),
-            limit: Some(30),
-        },
-    };
-    
-    let query_request = QueryRequest::Wasm(WasmQuery::Smart {
-        contract_addr: deps.api.addr_humanize(&sct_contract_address)?.to_string(),
-        code_hash: sct_code_hash,
-        msg: to_binary(&query_msg)?,
-    });
-    
-    // Execute the query
-    let query_result: Result<TokensResponse, _> = deps.querier.query(&query_request);
-    
-    match query_result {
-        Ok(response) => {
-            // Check if user has any tokens
-            let has_tokens = !response.token_list.tokens.is_empty();
-            if has_tokens {
-                Ok(true)
-            } else {
-                // Return error instead of false to make it explicit
-                Err(StdError::generic_err("No SCT tokens found for this address"))
-            }
-        }
-        Err(e) => {
-            // If query fails (e.g., invalid viewing key), return specific error
-            Err(StdError::generic_err(format!("SCT query failed: {}", e)))
-        }
+// Helper function to remove an edited poll's current content from the duplicate content index
+// (once it's edited again, deleted or archived)
+fn release_content_id(storage: &mut dyn Storage, poll: &Poll) -> StdResult<()> {
+    let content_id = generate_content_id(&poll.title, &poll.options, &poll.questions);
+    if content_id != poll.poll_id && EDITED_CONTENT.contains(storage, &content_id) {
+        EDITED_CONTENT.remove(storage, &content_id)?;
     }
+    Ok(())
 }
 
-
-
 // Helper function to check if poll is valid
 // Fails if:
-// - Invalid poll contents 
-//   - <2 options or >8 options
-//   - empty title or description
-//   - title length > 100 chars
-//   - description length > 500 chars
-// - The resulting poll_id already taken (same title+options)
+// - Invalid poll contents (see validate_poll_contents)
+// - The resulting poll_id already taken (same title+options, including edited and archived polls)
 fn validate_poll(
     title: &str,
     description: &str,
     options: &[String],
+    questions: &[SurveyQuestion],
     poll_id: &str,
     deps: Deps,
+) -> StdResult<()> {
+    validate_poll_contents(title, description, options, questions)?;
+    // Check if poll_id already taken (same title+options)
+    check_content_available(deps, poll_id)
+}
+
+// Helper function to check if a poll's contents are valid
+// Fails if:
+// - <2 options or >8 options (for surveys, in any question)
+// - a survey has options of its own, or <2 or >MAX_SURVEY_QUESTIONS questions
+// - a survey question is empty or longer than 200 chars
+// - a multiple choice question allows 0 choices, or more choices than it has options
+// - a question's condition refers to a later question (or itself), or has no options or invalid ones
+// - empty title or description
+// - title length > 100 chars
+// - description length > 500 chars
+fn validate_poll_contents(
+    title: &str,
+    description: &str,
+    options: &[String],
+    questions: &[SurveyQuestion],
 ) -> StdResult<()> {
     // Check if options are valid
-    if options.len() < 2 || options.len() > 8 {
-        return Err(StdError::generic_err("Invalid number of options (min 2, max 8)"));
+    if questions.is_empty() {
+        validate_options(options)?;
+    } else {
+        // Check if survey questions are valid
+        if !options.is_empty() {
+            return Err(StdError::generic_err("Surveys have options for each question, not for the whole survey"));
+        }
+        if questions.len() < 2 || questions.len() > MAX_SURVEY_QUESTIONS {
+            return Err(StdError::generic_err(format!("Invalid number of questions (min 2, max {})", MAX_SURVEY_QUESTIONS)));
+        }
+        for (i, question) in questions.iter().enumerate() {
+            if question.question.is_empty() || question.question.len() > 200 {
+                return Err(StdError::generic_err("Questions must be between 1 and 200 characters"));
+            }
+            validate_options(&question.options)?;
+            if let VotingMethod::MultipleChoice { max_choices } = question.method {
+                if max_choices == 0 || max_choices as usize > question.options.len() {
+                    return Err(StdError::generic_err("Invalid max_choices (min 1, max the number of options)"));
+                }
+            }
+            if let Some(condition) = &question.condition {
+                let earlier = questions[..i].get(condition.question as usize)
+                    .ok_or_else(|| StdError::generic_err("A question's condition must refer to an earlier question"))?;
+                if condition.options.is_empty()
+                    || condition.options.iter().any(|&option_idx| option_idx as usize >= earlier.options.len())
+                {
+                    return Err(StdError::generic_err("A question's condition must have valid options of the earlier question"));
+                }
+            }
+        }
     }
     // Check if title and description are valid
     if title.is_empty() || description.is_empty() {
@@ -377,9 +1921,420 @@ fn validate_poll(
     if description.len() > 500 {
         return Err(StdError::generic_err("Description too long (max 500 chars)"));
     }
-    // Check if poll_id already taken (same title+options)
-    if POLLS.contains(deps.storage, &poll_id.to_string()) {
-        return Err(StdError::generic_err("Poll ID already taken (same title and options)"));
+    Ok(())
+}
+
+
+// Helper function to get a poll's breakdown voters and cells, with small counts suppressed
+// Fails if the poll has no breakdown, or fewer than MIN_GROUP_SIZE voters
+fn get_breakdown_cells(storage: &dyn Storage, poll: &Poll) -> StdResult<(u32, Vec<Vec<Option<u32>>>)> {
+    let breakdown = poll.breakdown.as_ref()
+        .ok_or_else(|| StdError::generic_err("This poll has no breakdown"))?;
+    let counts = BREAKDOWN_COUNTS.get(storage, &poll.poll_id)
+        .unwrap_or_else(|| vec![vec![0; poll.options.len()]; breakdown.values.len() + 1]);
+    let voters: u32 = counts.iter().flatten().sum();
+    if voters < MIN_GROUP_SIZE {
+        return Err(StdError::generic_err(format!("Too few voters for a breakdown (min {})", MIN_GROUP_SIZE)));
+    }
+    Ok((voters, suppress_small_cells(&counts)))
+}
+
+// Helper function to get a cross-tab's respondents and cells, with small counts suppressed
+// Fails if fewer than MIN_GROUP_SIZE respondents answered both questions
+fn get_cross_tab_cells(tab_counts: CrossTabCounts) -> StdResult<(u32, Vec<Vec<Option<u32>>>)> {
+    if tab_counts.respondents < MIN_GROUP_SIZE {
+        return Err(StdError::generic_err(format!(
+            "Too few respondents answered both questions for a cross-tab (min {})", MIN_GROUP_SIZE
+        )));
+    }
+    Ok((tab_counts.respondents, suppress_small_cells(&tab_counts.counts)))
+}
+
+// Helper function to suppress cross-tab and breakdown cells counting between 1 and MIN_GROUP_SIZE - 1 respondents
+// Cells count respondents, not weights. On unweighted polls a row or column's total can be worked out
+// from the public tallies, so a row or column with a single suppressed cell would reveal it: the smallest
+// nonzero cell in it is suppressed too, until no row or column has exactly one suppressed cell
+// (a visible 0 would show the extra cell was 0 as well, and so give the small cell away)
+// If every other cell in it is 0, they're all suppressed, so the total doesn't show where the small cell was
+// (on weighted polls the tallies don't match the counts, so this is only a precaution)
+fn suppress_small_cells(counts: &[Vec<u32>]) -> Vec<Vec<Option<u32>>> {
+    
+    let mut cells: Vec<Vec<Option<u32>>> = counts.iter()
+        .map(|row| row.iter().map(|&count| (count == 0 || count >= MIN_GROUP_SIZE).then_some(count)).collect())
+        .collect();
+    let num_columns = counts.first().map_or(0, |row| row.len());
+    let lines: Vec<Vec<(usize, usize)>> = (0..counts.len())
+        .map(|row| (0..num_columns).map(|column| (row, column)).collect())
+        .chain((0..num_columns).map(|column| (0..counts.len()).map(|row| (row, column)).collect()))
+        .collect();
+    
+    loop {
+        let line = lines.iter().find(|line| {
+            line.iter().filter(|&&(row, column)| cells[row][column].is_none()).count() == 1
+                && line.iter().any(|&(row, column)| cells[row][column].is_some())
+        });
+        let line = match line {
+            Some(line) => line,
+            None => return cells,
+        };
+        let visible: Vec<(usize, usize)> = line.iter()
+            .copied()
+            .filter(|&(row, column)| cells[row][column].is_some())
+            .collect();
+        let smallest = visible.iter()
+            .filter(|&&(row, column)| cells[row][column] != Some(0))
+            .min_by_key(|&&(row, column)| cells[row][column]);
+        match smallest {
+            Some(&(row, column)) => cells[row][column] = None,
+            None => {
+                for (row, column) in visible {
+                    cells[row][column] = None;
+                }
+            }
+        }
+    }
+}
+
+// Helper function to check a survey's cross-tabs
+// Fails if:
+// - a single-question poll has cross-tabs
+// - there are more than MAX_CROSS_TABS cross-tabs, or the same one is listed twice
+// - a cross-tab's questions aren't two different questions of the survey
+fn validate_cross_tabs(questions: &[SurveyQuestion], cross_tabs: &[CrossTab]) -> StdResult<()> {
+    if cross_tabs.is_empty() {
+        return Ok(());
+    }
+    if questions.is_empty() {
+        return Err(StdError::generic_err("Cross-tabs are only available for surveys"));
+    }
+    if cross_tabs.len() > MAX_CROSS_TABS {
+        return Err(StdError::generic_err(format!("Too many cross-tabs (max {})", MAX_CROSS_TABS)));
+    }
+    for (i, cross_tab) in cross_tabs.iter().enumerate() {
+        if cross_tab.row_question == cross_tab.column_question
+            || cross_tab.row_question as usize >= questions.len()
+            || cross_tab.column_question as usize >= questions.len()
+        {
+            return Err(StdError::generic_err("Invalid cross-tab (must be two different questions of the survey)"));
+        }
+        if cross_tabs[..i].contains(cross_tab) {
+            return Err(StdError::generic_err("Duplicate cross-tab"));
+        }
+    }
+    Ok(())
+}
+
+// Helper function to create empty counts for each of a survey's cross-tabs
+fn new_cross_tab_counts(poll: &Poll) -> Vec<CrossTabCounts> {
+    poll.cross_tabs.iter()
+        .map(|cross_tab| CrossTabCounts {
+            respondents: 0,
+            counts: vec![
+                vec![0; poll.questions[cross_tab.column_question as usize].options.len()];
+                poll.questions[cross_tab.row_question as usize].options.len()
+            ],
+        })
+        .collect()
+}
+
+// Helper function to check a poll or survey question has between 2 and 8 options
+fn validate_options(options: &[String]) -> StdResult<()> {
+    if options.len() < 2 || options.len() > 8 {
+        return Err(StdError::generic_err("Invalid number of options (min 2, max 8)"));
+    }
+    Ok(())
+}
+
+
+// Helper function to get a user's nullifier within a scope (a poll_id, or another store keyed by user)
+// It is a hash of the address keyed with the contract's secret, so stored votes can't be linked
+// to addresses without the secret, and a voter's nullifiers in different polls can't be linked
+// The scope and address are each prefixed with their length, so no two (scope, address) pairs hash the same input
+fn voter_nullifier(
+    deps: Deps,
+    scope: &str,
+    address: &CanonicalAddr,
+) -> StdResult<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(NULLIFIER_SECRET.load(deps.storage)?);
+    hasher.update((scope.len() as u32).to_be_bytes());
+    hasher.update(scope.as_bytes());
+    hasher.update((address.len() as u32).to_be_bytes());
+    hasher.update(address.as_slice());
+    Ok(hex::encode(hasher.finalize()))
+}
+
+
+// Helper function to check the sender is the admin
+fn check_admin(deps: Deps, sender: &Addr) -> StdResult<()> {
+    let admin = ADMIN.load(deps.storage)?;
+    if deps.api.addr_canonicalize(sender.as_str())? != admin {
+        return Err(StdError::generic_err("Only the admin can do this"));
+    }
+    Ok(())
+}
+
+// Helper function to check if creating a poll in a community requires holding its SCT
+// The community's override takes precedence over the contract config
+fn requires_sct_to_create(deps: Deps, community: &Addr) -> StdResult<bool> {
+    match COMMUNITY_CREATION_RULES.get(deps.storage, &community.to_string()) {
+        Some(community) => Ok(community.require_sct_to_create),
+        None => Ok(CONFIG.load(deps.storage)?.require_sct_to_create),
+    }
+}
+
+// Helper function to check exactly the creation fee plus deposit was sent (in uscrt)
+fn check_creation_payment(funds: &[Coin], config: &Config) -> StdResult<()> {
+    let required = config.creation_fee + config.creation_deposit;
+    let paid = funds
+        .iter()
+        .try_fold(Uint128::zero(), |paid, coin| {
+            if coin.denom != FEE_DENOM {
+                return Err(StdError::generic_err(format!("Poll creation only accepts {}", FEE_DENOM)));
+            }
+            Ok(paid + coin.amount)
+        })?;
+    if paid != required {
+        return Err(StdError::generic_err(format!(
+            "Poll creation requires {} {} (fee {} + deposit {}), sent {}",
+            required, FEE_DENOM, config.creation_fee, config.creation_deposit, paid
+        )));
+    }
+    Ok(())
+}
+
+// Helper function to check a creator's rate limit and open poll quota, and record a new poll against them
+// Nothing is recorded for limits that aren't set
+// Fails if the creator has made max_polls_per_window polls in the current window,
+// or has max_open_polls polls still open, or the poll never closes while max_open_polls is set
+// (a poll that never closes would hold its place in the quota for good once it has votes)
+fn record_creator_activity(
+    deps: DepsMut,
+    config: &Config,
+    creator: &str,
+    poll_id: &str,
+    now: Timestamp,
+    closes_at: Option<Timestamp>,
+) -> StdResult<()> {
+    if config.max_polls_per_window == 0 && config.max_open_polls == 0 {
+        return Ok(());
+    }
+    let mut activity = CREATOR_ACTIVITY.get(deps.storage, &creator.to_string()).unwrap_or_default();
+    
+    if config.max_polls_per_window > 0 {
+        // Start a new window if the current one has passed
+        if now >= activity.window_start.plus_seconds(config.rate_limit_window) {
+            activity.window_start = now;
+            activity.polls_in_window = 0;
+        }
+        if activity.polls_in_window >= config.max_polls_per_window {
+            return Err(StdError::generic_err(format!(
+                "Too many polls created recently (max {} per {} seconds)", config.max_polls_per_window, config.rate_limit_window
+            )));
+        }
+        activity.polls_in_window += 1;
+    }
+    
+    if config.max_open_polls > 0 {
+        let closes_at = closes_at
+            .ok_or_else(|| StdError::generic_err("duration_seconds is required while open polls are limited (max_open_polls)"))?;
+        
+        // Forget polls that have closed
+        activity.open_polls.retain(|(_, poll_closes_at)| *poll_closes_at > now);
+        if activity.open_polls.len() >= config.max_open_polls as usize {
+            return Err(StdError::generic_err(format!("Too many open polls (max {})", config.max_open_polls)));
+        }
+        activity.open_polls.push((poll_id.to_string(), closes_at));
+    }
+    
+    CREATOR_ACTIVITY.insert(deps.storage, &creator.to_string(), &activity)
+}
+
+// Helper function to stop a poll counting towards its creator's open poll quota
+// (once it's deleted, archived or hidden)
+fn release_open_poll(storage: &mut dyn Storage, poll_id: &str) -> StdResult<()> {
+    if let Some(creator) = POLL_CREATORS.get(storage, &poll_id.to_string()) {
+        if let Some(mut activity) = CREATOR_ACTIVITY.get(storage, &creator) {
+            let open_polls = activity.open_polls.len();
+            activity.open_polls.retain(|(open_poll_id, _)| open_poll_id != poll_id);
+            if activity.open_polls.len() < open_polls {
+                CREATOR_ACTIVITY.insert(storage, &creator, &activity)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Helper function to remove a poll from `POLL_CREATORS` and its creator's `CREATED_POLLS`
+// (once it's deleted or archived, so get_my_polls only pages through live polls)
+fn remove_poll_creator(storage: &mut dyn Storage, poll_id: &str) -> StdResult<()> {
+    if let Some(creator) = POLL_CREATORS.get(storage, &poll_id.to_string()) {
+        POLL_CREATORS.remove(storage, &poll_id.to_string())?;
+        let created_polls = CREATED_POLLS.add_suffix(creator.as_bytes());
+        let position = created_polls.iter(storage)?
+            .position(|created_poll_id| created_poll_id.is_ok_and(|created_poll_id| created_poll_id == poll_id));
+        if let Some(position) = position {
+            created_polls.remove(storage, position as u32)?;
+        }
+    }
+    Ok(())
+}
+
+// Helper function to check a rate limit has a window
+// Fails if max_polls_per_window is set but rate_limit_window is 0
+fn validate_rate_limit(config: &Config) -> StdResult<()> {
+    if config.max_polls_per_window > 0 && config.rate_limit_window == 0 {
+        return Err(StdError::generic_err("rate_limit_window must be set when max_polls_per_window is"));
+    }
+    Ok(())
+}
+
+// Helper function to check if a poll is still open for voting
+fn is_poll_open(poll: &Poll, now: Timestamp) -> bool {
+    match poll.closes_at {
+        Some(closes_at) => now < closes_at,
+        None => true,
+    }
+}
+
+// Helper function to check if a poll is hidden from GetPolls
+// A moderator's decision takes precedence, otherwise polls are hidden once they have
+// flag_threshold flags (if the threshold is set)
+fn is_poll_hidden(poll: &Poll, config: &Config) -> bool {
+    match poll.moderation {
+        Some(ModerationAction::Hide) => true,
+        Some(ModerationAction::Restore) => false,
+        None => config.flag_threshold > 0 && poll.flags >= config.flag_threshold,
+    }
+}
+
+// Helper function to check whether a poll (or any of its survey questions) has votes
+fn has_votes(poll: &Poll) -> bool {
+    poll.tally.iter()
+        .chain(poll.questions.iter().flat_map(|question| question.tally.iter()))
+        .any(|count| !count.is_zero())
+}
+
+// Helper function to count the voters on a poll (or respondents to a survey)
+fn get_turnout(storage: &dyn Storage, poll_id: &str) -> StdResult<u32> {
+    let votes = VOTES.add_suffix(poll_id.as_bytes()).get_len(storage)?;
+    let ballots = SURVEY_BALLOTS.add_suffix(poll_id.as_bytes()).get_len(storage)?;
+    Ok(votes + ballots)
+}
+
+// Helper function to remove up to limit entries from a keymap
+// Returns the number of entries removed
+fn remove_keymap_entries<T: Serialize + DeserializeOwned>(
+    storage: &mut dyn Storage,
+    keymap: &Keymap<String, T>,
+    limit: u32,
+) -> StdResult<u32> {
+    if limit == 0 {
+        return Ok(0);
+    }
+    let keys = keymap.paging_keys(storage, 0, limit)?;
+    for key in &keys {
+        keymap.remove(storage, key)?;
+    }
+    Ok(keys.len() as u32)
+}
+
+// Helper function to move a hidden poll's deposit (if any) into the treasury's fees
+// Deposits are only forfeited when moderators hide a poll, and restoring the poll doesn't return them
+fn forfeit_deposit(deps: DepsMut, poll_id: &str) -> StdResult<()> {
+    let deposit = match POLL_DEPOSITS.get(deps.storage, &poll_id.to_string()) {
+        Some(deposit) => deposit,
+        None => return Ok(()),
+    };
+    POLL_DEPOSITS.remove(deps.storage, &poll_id.to_string())?;
+    let mut treasury = TREASURY.load(deps.storage)?;
+    treasury.deposits_held -= deposit.amount;
+    treasury.fees += deposit.amount;
+    TREASURY.save(deps.storage, &treasury)?;
+    Ok(())
+}
+
+// Helper function to check the sender is a moderator (or the admin)
+fn check_moderator(deps: Deps, sender: &Addr) -> StdResult<()> {
+    let sender = deps.api.addr_canonicalize(sender.as_str())?;
+    if sender != ADMIN.load(deps.storage)? && !MODERATORS.load(deps.storage)?.contains(&sender) {
+        return Err(StdError::generic_err("Only moderators can do this"));
+    }
+    Ok(())
+}
+
+// Helper function to check the sender created the poll (by comparing creator nullifiers)
+fn check_creator(deps: Deps, poll_id: &str, sender: &Addr) -> StdResult<()> {
+    let creator = POLL_CREATORS.get(deps.storage, &poll_id.to_string())
+        .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
+    let sender = deps.api.addr_canonicalize(sender.as_str())?;
+    if voter_nullifier(deps, "creator", &sender)? != creator {
+        return Err(StdError::generic_err("Only the poll's creator can do this"));
+    }
+    Ok(())
+}
+
+// Helper function to get a poll's public author from the creator's choice
+// Fails if the creator isn't registered for the chosen organiser
+fn get_poll_author(deps: Deps, creator: &Addr, public_author: Option<PublicAuthor>) -> StdResult<Option<PollAuthor>> {
+    match public_author {
+        None => Ok(None),
+        Some(PublicAuthor::Address {}) => Ok(Some(PollAuthor::Address { address: creator.clone() })),
+        Some(PublicAuthor::Organiser { name }) => {
+            let creator_canonical = deps.api.addr_canonicalize(creator.as_str())?;
+            let registered = ORGANISERS
+                .get(deps.storage, &name)
+                .is_some_and(|addresses| addresses.contains(&creator_canonical));
+            if !registered {
+                return Err(StdError::generic_err(format!("Sender is not a verified address of organiser {}", name)));
+            }
+            Ok(Some(PollAuthor::Organiser { name }))
+        }
+    }
+}
+
+// Helper function to check a relayed ballot and get the voter's address
+// Replays are caught by the vote nullifier and the ballot's created_at, so the nonce isn't stored;
+// it only makes each ballot's signature unique
+// Fails if:
+// - The ballot was signed for another chain or contract, or has been revoked
+// - The signature is invalid
+// - The ballot does not hold exactly one vote
+// - Empty nonce, or nonce length > 64 chars
+fn validate_ballot(
+    deps: Deps,
+    env: &Env,
+    ballot: &Permit<Ballot>,
+) -> StdResult<Addr> {
+    if ballot.params.chain_id != env.block.chain_id {
+        return Err(StdError::generic_err("Ballot was signed for a different chain"));
+    }
+    let account = secret_toolkit::permit::validate(
+        deps,
+        PREFIX_REVOKED_PERMITS,
+        ballot,
+        env.contract.address.to_string(),
+        None,
+    )?;
+    if ballot.params.permissions.len() != 1 {
+        return Err(StdError::generic_err("Ballot must hold exactly one vote"));
+    }
+    let nonce = &ballot.params.permissions[0].nonce;
+    if nonce.is_empty() || nonce.len() > 64 {
+        return Err(StdError::generic_err("Invalid ballot nonce (1 to 64 chars)"));
+    }
+    deps.api.addr_validate(&account)
+}
+
+
+// Helper function to check a permit grants the given permission
+// The owner permission grants every permission
+fn check_permission(
+    permit: &Permit<PollPermissions>,
+    permission: PollPermissions,
+) -> StdResult<()> {
+    if !permit.check_permission(&PollPermissions::Owner) && !permit.check_permission(&permission) {
+        return Err(StdError::generic_err(format!("Permit does not have the {:?} permission", permission).to_lowercase()));
     }
     Ok(())
 }
@@ -401,6 +2356,11 @@ fn validate_vote(
     let poll = POLLS.get(deps.storage, &poll_id.to_string())
         .ok_or_else(|| StdError::generic_err("Poll does not exist"))?;
     
+    // Surveys are answered with SubmitBallot instead
+    if !poll.questions.is_empty() {
+        return Err(StdError::generic_err("This poll is a survey, use SubmitBallot to answer it"));
+    }
+    
     // Check if option index is valid
     if option_idx as usize >= poll.options.len() {
         return Err(StdError::generic_err("Invalid option index"));
@@ -410,6 +2370,57 @@ fn validate_vote(
 }
 
 
+// Helper function to check a survey ballot answers every question it was shown according to its voting method
+// Returns whether each question was shown (its condition matched the earlier answer)
+// Fails if:
+// - there isn't exactly one answer per question
+// - a question that wasn't shown has an answer
+// - a single choice answer doesn't have exactly one option
+// - a multiple choice answer has no options, more than max_choices, or the same option twice
+// - an option index is out of range
+fn validate_answers(
+    questions: &[SurveyQuestion],
+    answers: &[Vec<u32>],
+) -> StdResult<Vec<bool>> {
+    
+    if answers.len() != questions.len() {
+        return Err(StdError::generic_err(format!("Expected answers to {} questions", questions.len())));
+    }
+    let mut shown = Vec::with_capacity(questions.len());
+    for (i, (question, chosen)) in questions.iter().zip(answers).enumerate() {
+        // Questions that weren't shown must be left unanswered
+        // (a question whose earlier question wasn't shown isn't shown either, as that answer is empty)
+        let is_shown = match &question.condition {
+            Some(condition) => answers[condition.question as usize].iter().any(|option_idx| condition.options.contains(option_idx)),
+            None => true,
+        };
+        shown.push(is_shown);
+        if !is_shown {
+            if !chosen.is_empty() {
+                return Err(StdError::generic_err(format!("Question {} wasn't shown, so it can't be answered", i)));
+            }
+            continue;
+        }
+        
+        let max_choices = match question.method {
+            VotingMethod::SingleChoice => 1,
+            VotingMethod::MultipleChoice { max_choices } => max_choices as usize,
+        };
+        if chosen.is_empty() || chosen.len() > max_choices {
+            return Err(StdError::generic_err(format!("Question {} takes between 1 and {} options", i, max_choices)));
+        }
+        if chosen.iter().any(|&option_idx| option_idx as usize >= question.options.len()) {
+            return Err(StdError::generic_err(format!("Invalid option index for question {}", i)));
+        }
+        if chosen.iter().enumerate().any(|(j, option_idx)| chosen[..j].contains(option_idx)) {
+            return Err(StdError::generic_err(format!("Question {} has the same option chosen twice", i)));
+        }
+    }
+    
+    Ok(shown)
+}
+
+
 // Helper function to get a user's vote on a poll
 // This function assumes that the user has voted but NOT that the user has voted
 // Returns None if user has not voted
@@ -419,15 +2430,27 @@ fn get_user_vote(
     address: &CanonicalAddr,
 ) -> StdResult<Option<u32>> {
     
-    // Create vote key (poll_id + hashed voter address)
-    let vote_key = format!("{}_{}", poll_id, hex::encode(address.as_slice()));
+    // Create vote key (the voter's nullifier for this poll)
+    let nullifier = voter_nullifier(deps, poll_id, address)?;
     
     // Get the vote if it exists
-    let vote = VOTES.get(deps.storage, &vote_key);
+    let vote = VOTES.add_suffix(poll_id.as_bytes()).get(deps.storage, &nullifier);
     
     Ok(vote)
 }
 
+// Helper function to get a user's vote on a poll, or their ballot if it's a survey
+fn get_user_poll_vote(
+    deps: Deps,
+    poll_id: String,
+    address: &CanonicalAddr,
+) -> StdResult<PollVote> {
+    let nullifier = voter_nullifier(deps, &poll_id, address)?;
+    let vote = VOTES.add_suffix(poll_id.as_bytes()).get(deps.storage, &nullifier);
+    let answers = SURVEY_BALLOTS.add_suffix(poll_id.as_bytes()).get(deps.storage, &nullifier);
+    Ok(PollVote { poll_id, vote, answers })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -460,6 +2483,7 @@ mod tests {
             "Valid Title",
             "Valid description",
             &vec!["Option A".to_string(), "Option B".to_string()],
+            &[],
             "unique_poll_id",
             deps.as_ref(),
         );
@@ -475,6 +2499,7 @@ mod tests {
             "Valid Title",
             "Valid description",
             &vec!["Only Option".to_string()],
+            &[],
             "unique_poll_id",
             deps.as_ref(),
         );
@@ -487,6 +2512,7 @@ mod tests {
             "Valid Title",
             "Valid description",
             &too_many_options,
+            &[],
             "unique_poll_id",
             deps.as_ref(),
         );
@@ -503,6 +2529,7 @@ mod tests {
             "",
             "Valid description",
             &vec!["Option A".to_string(), "Option B".to_string()],
+            &[],
             "unique_poll_id",
             deps.as_ref(),
         );
@@ -514,6 +2541,7 @@ mod tests {
             "Valid Title",
             "",
             &vec!["Option A".to_string(), "Option B".to_string()],
+            &[],
             "unique_poll_id",
             deps.as_ref(),
         );
@@ -531,6 +2559,7 @@ mod tests {
             &long_title,
             "Valid description",
             &vec!["Option A".to_string(), "Option B".to_string()],
+            &[],
             "unique_poll_id",
             deps.as_ref(),
         );
@@ -543,11 +2572,48 @@ mod tests {
             "Valid Title",
             &long_description,
             &vec!["Option A".to_string(), "Option B".to_string()],
+            &[],
             "unique_poll_id",
             deps.as_ref(),
         );
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Description too long"));
     }
+    
+    #[test]
+    fn test_voter_nullifier() {
+        let mut deps = mock_dependencies();
+        let address = CanonicalAddr::from(vec![1u8; 20]);
+        NULLIFIER_SECRET.save(deps.as_mut().storage, &vec![7u8; 32]).unwrap();
+        let nullifier = voter_nullifier(deps.as_ref(), "poll_a", &address).unwrap();
+        
+        // Nullifiers are deterministic, but differ between polls and don't contain the address
+        assert_eq!(nullifier, voter_nullifier(deps.as_ref(), "poll_a", &address).unwrap());
+        assert_ne!(nullifier, voter_nullifier(deps.as_ref(), "poll_b", &address).unwrap());
+        assert!(!nullifier.contains(&hex::encode(address.as_slice())));
+        
+        // Moving bytes between the scope and the address gives a different nullifier
+        let split = voter_nullifier(deps.as_ref(), "ab", &CanonicalAddr::from(b"cd".as_slice())).unwrap();
+        assert_ne!(split, voter_nullifier(deps.as_ref(), "abc", &CanonicalAddr::from(b"d".as_slice())).unwrap());
+        
+        // A different contract secret gives different nullifiers
+        NULLIFIER_SECRET.save(deps.as_mut().storage, &vec![8u8; 32]).unwrap();
+        assert_ne!(nullifier, voter_nullifier(deps.as_ref(), "poll_a", &address).unwrap());
+    }
+    
+    #[test]
+    fn test_suppress_small_cells() {
+        // A small cell's row is suppressed with its smallest other cell
+        let cells = suppress_small_cells(&[vec![2, 6, 9], vec![7, 8, 10]]);
+        assert_eq!(cells, vec![vec![None, None, Some(9)], vec![None, None, Some(10)]]);
+        
+        // Zeros aren't picked, as a visible 0 would show the other suppressed cell was 0 too
+        let cells = suppress_small_cells(&[vec![2], vec![0], vec![0], vec![9]]);
+        assert_eq!(cells, vec![vec![None], vec![Some(0)], vec![Some(0)], vec![None]]);
+        
+        // If every other cell is 0, they're all suppressed
+        let cells = suppress_small_cells(&[vec![3], vec![0], vec![0]]);
+        assert_eq!(cells, vec![vec![None], vec![None], vec![None]]);
+    }
 }
 
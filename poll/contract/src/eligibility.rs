@@ -0,0 +1,934 @@
+/*
+eligibility.rs
+
+Defines how the contract decides who may vote on a poll, and with what weight
+
+A poll's eligibility is a rule tree (see `EligibilityRule` in state.rs). Leaves are checked
+by querying the credential contracts (SNIP-721 and SNIP-20) with the voter's viewing keys or
+permits, or by verifying a Merkle proof. Credential data never leaves the contract.
+
+See `polling-contract-design.md` for more details.
+*/
+
+// Imports
+use cosmwasm_std::{
+    Addr, Deps, QueryRequest, StdError, StdResult, Uint128, WasmQuery, to_binary,
+};
+use serde::{Deserialize, Serialize};
+use secret_toolkit::permit::{Permit, validate as validate_permit};
+use sha2::{Sha256, Digest};
+use hex;
+use crate::msg::{ContractCredential, QueryAuth};
+use crate::state::{
+    SCT_CONTRACT_ADDRESS, SCT_CODE_HASH, PREFIX_REVOKED_PERMITS, COMMUNITY_CREATION_RULES, Breakdown, EligibilityRule, Poll, Snip20Requirement, Snip721Contract, TraitRequirement, TraitWeights,
+};
+
+
+// Limits on the size of an eligibility rule tree
+const MAX_RULE_DEPTH: u32 = 3;
+const MAX_RULE_LEAVES: u32 = 8;
+// Domain separation prefixes for allowlist leaves and inner nodes
+const MERKLE_LEAF_PREFIX: u8 = 0x00;
+const MERKLE_NODE_PREFIX: u8 = 0x01;
+
+
+// Credentials a voter supplies so the contract can check eligibility rules on their behalf
+pub struct VoterCredentials {
+    // Auth for the default SCT contract
+    pub sct_auth: QueryAuth,
+    // Auth for SNIP-20 rules without a matching entry in `credentials`
+    pub snip20_auth: Option<QueryAuth>,
+    // Proof for allowlist rules
+    pub merkle_proof: Vec<String>,
+    // Auth for specific contracts
+    pub credentials: Vec<ContractCredential>,
+}
+
+
+
+
+
+
+// Rule Evaluation ---------------------------------------------------------------------
+
+// Checks the voter is eligible to vote on the poll and returns their voting weight
+// The weight is the voter's trait weight on trait-weighted polls, their balance on
+// balance-weighted polls, and 1 otherwise
+pub fn get_voter_weight(
+    deps: Deps,
+    voter: &Addr,
+    poll: &Poll,
+    credentials: &VoterCredentials,
+) -> StdResult<Uint128> {
+    // Check the rule tree, noting the voter's balance if a leaf is balance-weighted
+    let mut balance_weight = None;
+    check_eligibility(deps, voter, &poll.eligibility, credentials, &mut balance_weight)?;
+
+    if let Some(trait_weights) = &poll.trait_weights {
+        let sct = default_sct_contract(deps)?;
+        let tokens = get_nft_tokens(deps, &sct, voter, &credentials.sct_auth)?;
+        let sct_attributes = get_nft_attributes(deps, &sct, voter, &credentials.sct_auth, &tokens)?;
+        let weight = get_trait_weight(&sct_attributes, trait_weights);
+        if weight.is_zero() {
+            return Err(StdError::generic_err("Your SCT carries no voting weight on this poll"));
+        }
+        return Ok(weight);
+    }
+
+    Ok(balance_weight.unwrap_or_else(Uint128::one))
+}
+
+// Recursively checks an eligibility rule
+// Returns an error explaining why the voter is not eligible
+fn check_eligibility(
+    deps: Deps,
+    voter: &Addr,
+    rule: &EligibilityRule,
+    credentials: &VoterCredentials,
+    balance_weight: &mut Option<Uint128>,
+) -> StdResult<()> {
+    match rule {
+        EligibilityRule::HoldsNft { contract } => {
+            let (contract, auth) = resolve_nft_contract(deps, contract, credentials)?;
+            get_nft_tokens(deps, &contract, voter, auth)?;
+            Ok(())
+        }
+        EligibilityRule::HasTraits { contract, traits } => {
+            let (contract, auth) = resolve_nft_contract(deps, contract, credentials)?;
+            let tokens = get_nft_tokens(deps, &contract, voter, auth)?;
+            let nft_attributes = get_nft_attributes(deps, &contract, voter, auth, &tokens)?;
+            if !nft_attributes.iter().any(|attributes| has_required_traits(attributes, traits)) {
+                return Err(StdError::generic_err("No SCT with the required traits found for this address"));
+            }
+            Ok(())
+        }
+        EligibilityRule::OnAllowlist { root } => {
+            verify_allowlist_proof(voter, root, &credentials.merkle_proof)
+        }
+        EligibilityRule::Snip20Balance { requirement } => {
+            let auth = find_credential(credentials, &requirement.contract_address)
+                .or(credentials.snip20_auth.as_ref())
+                .ok_or_else(|| StdError::generic_err("This is a token poll, snip20_auth is required"))?;
+            let balance = get_snip20_balance(deps, requirement, voter, auth)?;
+            if balance < requirement.min_balance {
+                return Err(StdError::generic_err("Token balance below the poll's minimum"));
+            }
+            if requirement.weight_by_balance {
+                *balance_weight = Some(balance);
+            }
+            Ok(())
+        }
+        EligibilityRule::All { rules } => {
+            for rule in rules {
+                check_eligibility(deps, voter, rule, credentials, balance_weight)?;
+            }
+            Ok(())
+        }
+        EligibilityRule::Any { rules } => {
+            let mut reasons = Vec::with_capacity(rules.len());
+            for rule in rules {
+                match check_eligibility(deps, voter, rule, credentials, balance_weight) {
+                    Ok(()) => return Ok(()),
+                    Err(e) => reasons.push(e.to_string()),
+                }
+            }
+            Err(StdError::generic_err(format!("None of the eligibility alternatives were met: {}", reasons.join("; "))))
+        }
+    }
+}
+
+// Checks the user meets the poll's eligibility rules, without working out their voting weight
+// Used for actions open to the poll's voters other than voting (e.g. flagging)
+pub fn check_voter_eligibility(
+    deps: Deps,
+    voter: &Addr,
+    poll: &Poll,
+    credentials: &VoterCredentials,
+) -> StdResult<()> {
+    check_eligibility(deps, voter, &poll.eligibility, credentials, &mut None)
+}
+
+// Gets the voter's group in a poll's breakdown from their SCTs' attributes
+// Returns the index of the first breakdown value any of their SCTs carries, or the number of
+// values (the "other" group) if none do
+pub fn get_breakdown_group(
+    deps: Deps,
+    voter: &Addr,
+    breakdown: &Breakdown,
+    credentials: &VoterCredentials,
+) -> StdResult<usize> {
+    let sct = default_sct_contract(deps)?;
+    let tokens = get_nft_tokens(deps, &sct, voter, &credentials.sct_auth)?;
+    let sct_attributes = get_nft_attributes(deps, &sct, voter, &credentials.sct_auth, &tokens)?;
+    let group = breakdown.values.iter().position(|value| {
+        sct_attributes.iter().flatten().any(|attribute| {
+            attribute.trait_type.as_deref() == Some(breakdown.trait_type.as_str()) && &attribute.value == value
+        })
+    });
+    Ok(group.unwrap_or(breakdown.values.len()))
+}
+
+// Checks the creator of a poll holds an SCT of one of the poll's communities
+// Uses the same credential check as voting on a `holds_nft` rule
+pub fn check_creator_credential(
+    deps: Deps,
+    creator: &Addr,
+    community: &Snip721Contract,
+    credentials: &VoterCredentials,
+) -> StdResult<()> {
+    let rule = EligibilityRule::HoldsNft { contract: Some(community.clone()) };
+    check_eligibility(deps, creator, &rule, credentials, &mut None)
+}
+
+// Gets the communities of a poll: every SNIP-721 contract in its eligibility rule tree that the admin
+// has registered as a community (with its registered code hash), plus the default SCT contract's
+// community if the tree names the default SCT contract, or names no registered community
+// A creator must meet the creation rules of all of them, so they can't choose the contract their own
+// credential is checked against, or skip a community's requirement by naming another community too
+pub fn get_poll_communities(
+    deps: Deps,
+    rule: &EligibilityRule,
+) -> StdResult<Vec<Snip721Contract>> {
+    let sct = default_sct_contract(deps)?;
+    let mut communities = vec![];
+    let mut names_sct = false;
+    find_communities(deps, rule, &sct.address, &mut communities, &mut names_sct);
+    if (names_sct || communities.is_empty()) && !communities.iter().any(|community| community.address == sct.address) {
+        communities.push(sct);
+    }
+    Ok(communities)
+}
+
+// Helper function to find the registered community contracts named in a rule tree,
+// and whether it names the default SCT contract
+fn find_communities(
+    deps: Deps,
+    rule: &EligibilityRule,
+    sct_address: &Addr,
+    communities: &mut Vec<Snip721Contract>,
+    names_sct: &mut bool,
+) {
+    match rule {
+        EligibilityRule::HoldsNft { contract } | EligibilityRule::HasTraits { contract, .. } => {
+            let address = match contract {
+                Some(contract) => &contract.address,
+                None => sct_address,
+            };
+            *names_sct |= address == sct_address;
+            if let Some(community) = COMMUNITY_CREATION_RULES.get(deps.storage, &address.to_string()) {
+                if !communities.iter().any(|found| found.address == *address) {
+                    communities.push(Snip721Contract { address: address.clone(), code_hash: community.code_hash });
+                }
+            }
+        }
+        EligibilityRule::All { rules } | EligibilityRule::Any { rules } => {
+            for rule in rules {
+                find_communities(deps, rule, sct_address, communities, names_sct);
+            }
+        }
+        EligibilityRule::OnAllowlist { .. } | EligibilityRule::Snip20Balance { .. } => {}
+    }
+}
+
+// Helper function to find the voter's credentials for a specific contract
+fn find_credential<'a>(
+    credentials: &'a VoterCredentials,
+    contract_address: &Addr,
+) -> Option<&'a QueryAuth> {
+    credentials.credentials.iter()
+        .find(|credential| credential.contract_address == *contract_address)
+        .map(|credential| &credential.auth)
+}
+
+// Helper function to get the default SCT contract set at instantiation
+fn default_sct_contract(
+    deps: Deps,
+) -> StdResult<Snip721Contract> {
+    Ok(Snip721Contract {
+        address: deps.api.addr_humanize(&SCT_CONTRACT_ADDRESS.load(deps.storage)?)?,
+        code_hash: SCT_CODE_HASH.load(deps.storage)?,
+    })
+}
+
+// Helper function to resolve a rule's SNIP-721 contract and the voter's credentials for it
+// The default SCT contract falls back to the voter's SCT auth
+fn resolve_nft_contract<'a>(
+    deps: Deps,
+    contract: &Option<Snip721Contract>,
+    credentials: &'a VoterCredentials,
+) -> StdResult<(Snip721Contract, &'a QueryAuth)> {
+    let sct = default_sct_contract(deps)?;
+    let contract = contract.clone().unwrap_or(sct.clone());
+    let auth = match find_credential(credentials, &contract.address) {
+        Some(auth) => auth,
+        None if contract.address == sct.address => &credentials.sct_auth,
+        None => return Err(StdError::generic_err(format!("Missing credentials for contract {}", contract.address))),
+    };
+    Ok((contract, auth))
+}
+
+// Helper function to check that a permit for a credential contract was signed by the voter
+// Credential contracts answer permit queries for the permit's signer, so it must be the voter
+fn check_permit_signer(
+    deps: Deps,
+    permit: &Permit,
+    contract_address: &Addr,
+    voter: &Addr,
+) -> StdResult<()> {
+    let signer = validate_permit(deps, PREFIX_REVOKED_PERMITS, permit, contract_address.to_string(), None)?;
+    if signer != voter.as_str() {
+        return Err(StdError::generic_err("Permit was not signed by the voter"));
+    }
+    Ok(())
+}
+
+
+
+
+
+
+// SNIP-721 Credentials ---------------------------------------------------------------------
+
+// SNIP-721 query structures
+#[derive(Serialize, Deserialize)]
+struct TokensQuery {
+    tokens: TokensQueryParams,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TokensQueryParams {
+    owner: String,
+    viewing_key: String,
+    limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TokensResponse {
+    token_list: TokenList,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TokenList {
+    tokens: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NftDossierQuery {
+    nft_dossier: NftDossierQueryParams,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NftDossierQueryParams {
+    token_id: String,
+    viewer: ViewerInfo,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ViewerInfo {
+    address: String,
+    viewing_key: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snip721PermitQuery {
+    with_permit: Snip721PermitQueryParams,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snip721PermitQueryParams {
+    permit: Permit,
+    query: Snip721QueryWithPermit,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Snip721QueryWithPermit {
+    Tokens { owner: String, limit: Option<u32> },
+    NftDossier { token_id: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct NftDossierResponse {
+    nft_dossier: NftDossier,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NftDossier {
+    public_metadata: Option<Metadata>,
+    private_metadata: Option<Metadata>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Metadata {
+    extension: Option<Extension>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Extension {
+    attributes: Option<Vec<Trait>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Trait {
+    trait_type: Option<String>,
+    value: String,
+}
+
+// Helper function to get the tokens the voter owns on an SNIP-721 contract
+// Returns an error if the voter owns no tokens or the query fails
+fn get_nft_tokens(
+    deps: Deps,
+    contract: &Snip721Contract,
+    voter: &Addr,
+    auth: &QueryAuth,
+) -> StdResult<Vec<String>> {
+
+    // Create the proper SNIP-721 tokens query
+    let msg = match auth {
+        QueryAuth::ViewingKey { key } => to_binary(&TokensQuery {
+            tokens: TokensQueryParams {
+                owner: voter.to_string(),
+                viewing_key: key.clone(),
+                limit: Some(30),
+            },
+        })?,
+        QueryAuth::Permit { permit } => {
+            check_permit_signer(deps, permit, &contract.address, voter)?;
+            to_binary(&Snip721PermitQuery {
+                with_permit: Snip721PermitQueryParams {
+                    permit: permit.clone(),
+                    query: Snip721QueryWithPermit::Tokens { owner: voter.to_string(), limit: Some(30) },
+                },
+            })?
+        }
+    };
+
+    let query_request = QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: contract.address.to_string(),
+        code_hash: contract.code_hash.clone(),
+        msg,
+    });
+
+    // Execute the query
+    let query_result: Result<TokensResponse, _> = deps.querier.query(&query_request);
+
+    match query_result {
+        Ok(response) => {
+            // Check if user has any tokens
+            if response.token_list.tokens.is_empty() {
+                return Err(StdError::generic_err("No SCT tokens found for this address"));
+            }
+            Ok(response.token_list.tokens)
+        }
+        Err(e) => {
+            // If query fails (e.g., invalid viewing key), return specific error
+            Err(StdError::generic_err(format!("SCT query failed: {}", e)))
+        }
+    }
+}
+
+// Helper function to get the metadata attributes of each of the given tokens
+// Public and private metadata are read with the owner's credentials and never leave the contract
+fn get_nft_attributes(
+    deps: Deps,
+    contract: &Snip721Contract,
+    voter: &Addr,
+    auth: &QueryAuth,
+    tokens: &[String],
+) -> StdResult<Vec<Vec<Trait>>> {
+    let mut nft_attributes = Vec::with_capacity(tokens.len());
+    for token_id in tokens {
+        let msg = match auth {
+            QueryAuth::ViewingKey { key } => to_binary(&NftDossierQuery {
+                nft_dossier: NftDossierQueryParams {
+                    token_id: token_id.clone(),
+                    viewer: ViewerInfo {
+                        address: voter.to_string(),
+                        viewing_key: key.clone(),
+                    },
+                },
+            })?,
+            QueryAuth::Permit { permit } => {
+                check_permit_signer(deps, permit, &contract.address, voter)?;
+                to_binary(&Snip721PermitQuery {
+                    with_permit: Snip721PermitQueryParams {
+                        permit: permit.clone(),
+                        query: Snip721QueryWithPermit::NftDossier { token_id: token_id.clone() },
+                    },
+                })?
+            }
+        };
+
+        let query_request = QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: contract.address.to_string(),
+            code_hash: contract.code_hash.clone(),
+            msg,
+        });
+
+        let response: NftDossierResponse = deps.querier.query(&query_request)
+            .map_err(|e| StdError::generic_err(format!("SCT metadata query failed: {}", e)))?;
+
+        // Collect the token's attributes from both public and private metadata
+        let dossier = response.nft_dossier;
+        let attributes: Vec<Trait> = [dossier.public_metadata, dossier.private_metadata]
+            .into_iter()
+            .flatten()
+            .filter_map(|metadata| metadata.extension.and_then(|extension| extension.attributes))
+            .flatten()
+            .collect();
+        nft_attributes.push(attributes);
+    }
+
+    Ok(nft_attributes)
+}
+
+// Helper function to check if a single token's attributes include every required trait
+fn has_required_traits(
+    attributes: &[Trait],
+    required_traits: &[TraitRequirement],
+) -> bool {
+    required_traits.iter().all(|required| {
+        attributes.iter().any(|attribute| {
+            attribute.trait_type.as_deref() == Some(required.trait_type.as_str())
+                && attribute.value == required.value
+        })
+    })
+}
+
+// Helper function to get a voter's weight from their SCTs' attributes
+// Returns the highest weight among the SCTs, or the default weight if none match
+fn get_trait_weight(
+    sct_attributes: &[Vec<Trait>],
+    trait_weights: &TraitWeights,
+) -> Uint128 {
+    sct_attributes
+        .iter()
+        .flatten()
+        .filter(|attribute| attribute.trait_type.as_deref() == Some(trait_weights.trait_type.as_str()))
+        .filter_map(|attribute| {
+            trait_weights.weights.iter()
+                .find(|trait_weight| trait_weight.value == attribute.value)
+                .map(|trait_weight| trait_weight.weight)
+        })
+        .max()
+        .unwrap_or(trait_weights.default_weight)
+}
+
+
+
+
+
+
+// SNIP-20 Credentials ---------------------------------------------------------------------
+
+// SNIP-20 query structures
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Snip20Query {
+    Balance { address: String, key: String },
+    WithPermit { permit: Permit, query: Snip20PermitQuery },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Snip20PermitQuery {
+    Balance {},
+}
+
+#[derive(Serialize, Deserialize)]
+struct BalanceResponse {
+    balance: Balance,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Balance {
+    amount: Uint128,
+}
+
+// Helper function to get the voter's SNIP-20 balance
+// The balance is read live at vote time; nothing stops the same tokens being moved and counted again
+fn get_snip20_balance(
+    deps: Deps,
+    requirement: &Snip20Requirement,
+    voter: &Addr,
+    auth: &QueryAuth,
+) -> StdResult<Uint128> {
+
+    // Build the balance query, with either the voter's viewing key or their permit
+    let query_msg = match auth {
+        QueryAuth::ViewingKey { key } => Snip20Query::Balance {
+            address: voter.to_string(),
+            key: key.clone(),
+        },
+        QueryAuth::Permit { permit } => {
+            check_permit_signer(deps, permit, &requirement.contract_address, voter)?;
+            Snip20Query::WithPermit {
+                permit: permit.clone(),
+                query: Snip20PermitQuery::Balance {},
+            }
+        }
+    };
+
+    let query_request = QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: requirement.contract_address.to_string(),
+        code_hash: requirement.code_hash.clone(),
+        msg: to_binary(&query_msg)?,
+    });
+
+    let response: BalanceResponse = deps.querier.query(&query_request)
+        .map_err(|e| StdError::generic_err(format!("SNIP-20 balance query failed: {}", e)))?;
+
+    Ok(response.balance.amount)
+}
+
+
+
+
+
+
+// Merkle Allowlists ---------------------------------------------------------------------
+
+// Helper function to check that an address is on a Merkle allowlist
+// Leaves are sha256(0x00 + address) and each parent is sha256(0x01 + its two children in sorted order),
+// so the proof is just the list of sibling hashes from the leaf up to the root
+// The prefixes keep a leaf from ever hashing the same as an inner node
+fn verify_allowlist_proof(
+    address: &Addr,
+    allowlist_root: &str,
+    merkle_proof: &[String],
+) -> StdResult<()> {
+    if merkle_proof.len() > 32 {
+        return Err(StdError::generic_err("Merkle proof too long (max 32 hashes)"));
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update([MERKLE_LEAF_PREFIX]);
+    hasher.update(address.as_bytes());
+    let mut hash: [u8; 32] = hasher.finalize().into();
+    for sibling in merkle_proof {
+        let sibling: [u8; 32] = hex::decode(sibling).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| StdError::generic_err("Invalid Merkle proof hash"))?;
+        let mut hasher = Sha256::new();
+        hasher.update([MERKLE_NODE_PREFIX]);
+        if hash <= sibling {
+            hasher.update(hash);
+            hasher.update(sibling);
+        } else {
+            hasher.update(sibling);
+            hasher.update(hash);
+        }
+        hash = hasher.finalize().into();
+    }
+
+    if hex::encode(hash) != allowlist_root {
+        return Err(StdError::generic_err("Address is not on this poll's allowlist"));
+    }
+    Ok(())
+}
+
+
+
+
+
+
+// Validation ---------------------------------------------------------------------
+
+// Helper function to check if an eligibility rule tree is valid
+// Fails if:
+// - The tree is deeper than 3 levels or has more than 8 leaves
+// - An `all`/`any` rule has no rules
+// - A leaf is invalid (see the leaf validation functions below)
+// - More than one SNIP-20 rule is balance-weighted, or one is inside an `any` rule
+// - A SNIP-20 rule is balance-weighted and the poll also has trait weights
+// - The poll has trait weights, but voters don't have to hold a default SCT (weights are read from it)
+pub fn validate_eligibility_rule(
+    rule: &EligibilityRule,
+    trait_weights: &Option<TraitWeights>,
+    deps: Deps,
+) -> StdResult<()> {
+    let mut num_leaves = 0;
+    let mut num_balance_weighted = 0;
+    validate_rule_node(rule, 1, false, &mut num_leaves, &mut num_balance_weighted, deps)?;
+    if num_balance_weighted > 1 {
+        return Err(StdError::generic_err("Only one SNIP-20 rule can weight votes by balance"));
+    }
+    if num_balance_weighted == 1 && trait_weights.is_some() {
+        return Err(StdError::generic_err("Polls cannot be weighted by both balance and traits"));
+    }
+    if trait_weights.is_some() && !requires_default_sct(deps, rule)? {
+        return Err(StdError::generic_err("Trait-weighted polls must require the default SCT, as weights are read from it"));
+    }
+    Ok(())
+}
+
+// Checks whether every voter meeting a rule tree must hold a default SCT
+// That's the case if a `holds_nft` or `has_traits` rule on the default SCT contract is required,
+// either directly, in an `all` rule, or in every alternative of an `any` rule
+pub fn requires_default_sct(
+    deps: Deps,
+    rule: &EligibilityRule,
+) -> StdResult<bool> {
+    let sct = default_sct_contract(deps)?;
+    Ok(rule_requires_contract(rule, &sct.address))
+}
+
+// Recursively checks whether a rule tree requires holding a token of the given SNIP-721 contract
+fn rule_requires_contract(
+    rule: &EligibilityRule,
+    address: &Addr,
+) -> bool {
+    match rule {
+        EligibilityRule::HoldsNft { contract } | EligibilityRule::HasTraits { contract, .. } => {
+            match contract {
+                Some(contract) => contract.address == *address,
+                None => true,
+            }
+        }
+        EligibilityRule::All { rules } => rules.iter().any(|rule| rule_requires_contract(rule, address)),
+        EligibilityRule::Any { rules } => rules.iter().all(|rule| rule_requires_contract(rule, address)),
+        EligibilityRule::OnAllowlist { .. } | EligibilityRule::Snip20Balance { .. } => false,
+    }
+}
+
+// Recursively checks a node of an eligibility rule tree
+fn validate_rule_node(
+    rule: &EligibilityRule,
+    depth: u32,
+    inside_any: bool,
+    num_leaves: &mut u32,
+    num_balance_weighted: &mut u32,
+    deps: Deps,
+) -> StdResult<()> {
+    if depth > MAX_RULE_DEPTH {
+        return Err(StdError::generic_err("Eligibility rule too deep (max 3 levels)"));
+    }
+
+    match rule {
+        EligibilityRule::All { rules } | EligibilityRule::Any { rules } => {
+            if rules.is_empty() {
+                return Err(StdError::generic_err("Eligibility all/any rules cannot be empty"));
+            }
+            let inside_any = inside_any || matches!(rule, EligibilityRule::Any { .. });
+            for rule in rules {
+                validate_rule_node(rule, depth + 1, inside_any, num_leaves, num_balance_weighted, deps)?;
+            }
+            return Ok(());
+        }
+        EligibilityRule::HoldsNft { contract } => {
+            validate_snip721_contract(contract, deps)?;
+        }
+        EligibilityRule::HasTraits { contract, traits } => {
+            validate_snip721_contract(contract, deps)?;
+            if traits.is_empty() {
+                return Err(StdError::generic_err("Trait rules need at least one trait"));
+            }
+            validate_required_traits(traits)?;
+        }
+        EligibilityRule::OnAllowlist { root } => {
+            validate_allowlist_root(root)?;
+        }
+        EligibilityRule::Snip20Balance { requirement } => {
+            validate_snip20_requirement(requirement, deps)?;
+            if requirement.weight_by_balance {
+                if inside_any {
+                    return Err(StdError::generic_err("Balance-weighted SNIP-20 rules cannot be inside an any rule"));
+                }
+                *num_balance_weighted += 1;
+            }
+        }
+    }
+
+    *num_leaves += 1;
+    if *num_leaves > MAX_RULE_LEAVES {
+        return Err(StdError::generic_err("Too many eligibility rules (max 8)"));
+    }
+    Ok(())
+}
+
+// Helper function to check if a rule's SNIP-721 contract is valid
+fn validate_snip721_contract(
+    contract: &Option<Snip721Contract>,
+    deps: Deps,
+) -> StdResult<()> {
+    if let Some(contract) = contract {
+        deps.api.addr_validate(contract.address.as_str())?;
+        if contract.code_hash.is_empty() {
+            return Err(StdError::generic_err("SNIP-721 code hash cannot be empty"));
+        }
+    }
+    Ok(())
+}
+
+// Helper function to check if a trait rule's traits are valid
+// Fails if:
+// - More than 5 required traits
+// - Empty trait_type or value
+// - trait_type or value length > 50 chars
+fn validate_required_traits(
+    required_traits: &[TraitRequirement],
+) -> StdResult<()> {
+    if required_traits.len() > 5 {
+        return Err(StdError::generic_err("Too many required traits (max 5)"));
+    }
+    for required in required_traits {
+        if required.trait_type.is_empty() || required.value.is_empty() {
+            return Err(StdError::generic_err("Required trait type and value cannot be empty"));
+        }
+        if required.trait_type.len() > 50 || required.value.len() > 50 {
+            return Err(StdError::generic_err("Required trait too long (max 50 chars)"));
+        }
+    }
+    Ok(())
+}
+
+// Helper function to check if a SNIP-20 rule is valid
+// Fails if:
+// - Invalid token contract address or empty code hash
+// - min_balance is 0
+fn validate_snip20_requirement(
+    requirement: &Snip20Requirement,
+    deps: Deps,
+) -> StdResult<()> {
+    deps.api.addr_validate(requirement.contract_address.as_str())?;
+    if requirement.code_hash.is_empty() {
+        return Err(StdError::generic_err("SNIP-20 code hash cannot be empty"));
+    }
+    if requirement.min_balance.is_zero() {
+        return Err(StdError::generic_err("SNIP-20 minimum balance must be greater than 0"));
+    }
+    Ok(())
+}
+
+// Helper function to check if an allowlist rule's Merkle root is valid
+// Fails if the root is not a lowercase hex SHA-256 hash (64 chars)
+fn validate_allowlist_root(
+    allowlist_root: &str,
+) -> StdResult<()> {
+    if allowlist_root.len() != 64 || !allowlist_root.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)) {
+        return Err(StdError::generic_err("Invalid allowlist root (expected 64 lowercase hex chars)"));
+    }
+    Ok(())
+}
+
+// Helper function to check if a poll's trait weights are valid
+// Fails if:
+// - Empty trait_type, or no weights
+// - More than 20 weights
+// - Empty or duplicate weight values
+// - trait_type or value length > 50 chars
+// - A zero weight (use a default_weight of 0 to exclude unmatched SCTs instead)
+pub fn validate_trait_weights(
+    trait_weights: &TraitWeights,
+) -> StdResult<()> {
+    if trait_weights.trait_type.is_empty() || trait_weights.weights.is_empty() {
+        return Err(StdError::generic_err("Weighted polls need a trait type and at least one weight"));
+    }
+    if trait_weights.weights.len() > 20 {
+        return Err(StdError::generic_err("Too many trait weights (max 20)"));
+    }
+    if trait_weights.trait_type.len() > 50 {
+        return Err(StdError::generic_err("Weight trait type too long (max 50 chars)"));
+    }
+    for (i, trait_weight) in trait_weights.weights.iter().enumerate() {
+        if trait_weight.value.is_empty() || trait_weight.value.len() > 50 {
+            return Err(StdError::generic_err("Invalid weight value (1 to 50 chars)"));
+        }
+        if trait_weight.weight.is_zero() {
+            return Err(StdError::generic_err("Trait weights must be greater than 0"));
+        }
+        if trait_weights.weights[..i].iter().any(|other| other.value == trait_weight.value) {
+            return Err(StdError::generic_err("Duplicate weight value"));
+        }
+    }
+    Ok(())
+}
+
+// Helper function to check if a poll's breakdown is valid
+// Fails if:
+// - Empty trait_type, or no values
+// - More than 20 values
+// - Empty or duplicate values
+// - trait_type or value length > 50 chars
+pub fn validate_breakdown(
+    breakdown: &Breakdown,
+) -> StdResult<()> {
+    if breakdown.trait_type.is_empty() || breakdown.values.is_empty() {
+        return Err(StdError::generic_err("Breakdowns need a trait type and at least one value"));
+    }
+    if breakdown.values.len() > 20 {
+        return Err(StdError::generic_err("Too many breakdown values (max 20)"));
+    }
+    if breakdown.trait_type.len() > 50 {
+        return Err(StdError::generic_err("Breakdown trait type too long (max 50 chars)"));
+    }
+    for (i, value) in breakdown.values.iter().enumerate() {
+        if value.is_empty() || value.len() > 50 {
+            return Err(StdError::generic_err("Invalid breakdown value (1 to 50 chars)"));
+        }
+        if breakdown.values[..i].contains(value) {
+            return Err(StdError::generic_err("Duplicate breakdown value"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::mock_dependencies;
+    use crate::state::TraitWeight;
+
+    #[test]
+    fn test_get_trait_weight() {
+        let trait_weights = TraitWeights {
+            trait_type: "tier".to_string(),
+            weights: vec![
+                TraitWeight { value: "gold".to_string(), weight: Uint128::new(10) },
+                TraitWeight { value: "silver".to_string(), weight: Uint128::new(3) },
+            ],
+            default_weight: Uint128::new(1),
+        };
+        let tier = |value: &str| Trait { trait_type: Some("tier".to_string()), value: value.to_string() };
+
+        // Highest weight among the voter's SCTs is used
+        let sct_attributes = vec![vec![tier("silver")], vec![tier("gold")]];
+        assert_eq!(get_trait_weight(&sct_attributes, &trait_weights), Uint128::new(10));
+
+        // Unmatched values and other trait types fall back to the default weight
+        let sct_attributes = vec![vec![tier("bronze"), Trait { trait_type: Some("role".to_string()), value: "gold".to_string() }]];
+        assert_eq!(get_trait_weight(&sct_attributes, &trait_weights), Uint128::new(1));
+    }
+
+    #[test]
+    fn test_validate_eligibility_rule_limits() {
+        let deps = mock_dependencies();
+        let sct = || EligibilityRule::HoldsNft { contract: None };
+
+        // "member AND (board OR committee)" is valid
+        let rule = EligibilityRule::All { rules: vec![
+            sct(),
+            EligibilityRule::Any { rules: vec![sct(), sct()] },
+        ] };
+        assert!(validate_eligibility_rule(&rule, &None, deps.as_ref()).is_ok());
+
+        // Empty all/any rules
+        let rule = EligibilityRule::Any { rules: vec![] };
+        let result = validate_eligibility_rule(&rule, &None, deps.as_ref());
+        assert!(result.unwrap_err().to_string().contains("cannot be empty"));
+
+        // Too deep
+        let rule = EligibilityRule::All { rules: vec![
+            EligibilityRule::Any { rules: vec![
+                EligibilityRule::All { rules: vec![sct()] },
+            ] },
+        ] };
+        let result = validate_eligibility_rule(&rule, &None, deps.as_ref());
+        assert!(result.unwrap_err().to_string().contains("too deep"));
+
+        // Too many leaves
+        let rule = EligibilityRule::Any { rules: (0..9).map(|_| sct()).collect() };
+        let result = validate_eligibility_rule(&rule, &None, deps.as_ref());
+        assert!(result.unwrap_err().to_string().contains("Too many eligibility rules"));
+    }
+}
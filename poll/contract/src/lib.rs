@@ -1,3 +1,4 @@
 pub mod contract;
+pub mod eligibility;
 pub mod msg;
 pub mod state;
@@ -7,9 +7,9 @@ Defines the messages that can be sent to the contract
 // Imports
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Timestamp, Uint128};
 use secret_toolkit::permit::Permit;
-use crate::state::Poll;
+use crate::state::{AuditEntry, Breakdown, Config, CrossTab, EligibilityRule, Poll, PollDraft, PollResult, PollRevision, QuestionCondition, TraitWeights, Treasury, VotingMethod};
 
 
 // InstantiateMsg is kinda like defining the parameters of the contract's constructor
@@ -17,25 +17,281 @@ use crate::state::Poll;
 pub struct InstantiateMsg {
     pub sct_contract_address: Addr,
     pub sct_code_hash: String,
+    // Manages the organiser registry and config (defaults to the instantiator)
+    pub admin: Option<Addr>,
+    // Require an SCT of the poll's community to create polls (defaults to false)
+    pub require_sct_to_create: Option<bool>,
+    // Poll creation fee and refundable deposit in uscrt (default to 0)
+    pub creation_fee: Option<Uint128>,
+    pub creation_deposit: Option<Uint128>,
+    // Votes a poll needs before its deposit can be reclaimed (defaults to 0)
+    pub deposit_refund_turnout: Option<u32>,
+    // Per-creator limits on polls per window and open polls (default to 0, no limit)
+    pub max_polls_per_window: Option<u32>,
+    pub rate_limit_window: Option<u64>,
+    pub max_open_polls: Option<u32>,
+    // Number of flags that hides a poll from GetPolls (defaults to 0, never hidden)
+    pub flag_threshold: Option<u32>,
 }
 
 
 // ExecuteMsg defines the messages that can modify the contract state
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     // Create a new poll with title, description, and voting options
+    // Optionally set who may vote with an eligibility rule tree (defaults to any SCT holder)
+    // Warning: balance-weighted SNIP-20 rules read the voter's live balance when they vote, so tokens
+    // transferred to another voter afterwards are counted again (there's no balance snapshot)
+    // Optionally weight votes by an SCT metadata trait (read from the default SCT, so eligibility must require one)
+    // Optionally break the results down by an SCT metadata trait (single-question polls only, read from the default SCT like weights)
+    // Optionally show a public author (polls are anonymous by default)
+    // Optionally close the poll after duration_seconds (polls stay open forever by default, but must close while max_open_polls is set)
+    // Set draft to save the poll privately instead, to be revised and published later with PublishPoll
+    // Set questions (and leave options empty) to make a multi-question survey, answered with SubmitBallot
+    // Surveys can list pairs of questions in cross_tabs to report their answers against each other with GetCrossTab
+    // Must be sent with the config's creation fee plus deposit in uscrt (unless it's a draft)
+    // If one of the poll's communities requires an SCT to create polls, the creator proves they hold one:
+    // - sct_auth for the default SCT contract
+    // - credentials for any other SNIP-721 contract (overrides the above)
     MakePoll {
         title: String,
         description: String,
         options: Vec<String>,
+        questions: Option<Vec<Question>>,
+        cross_tabs: Option<Vec<CrossTab>>,
+        eligibility: Option<EligibilityRule>,
+        trait_weights: Option<TraitWeights>,
+        breakdown: Option<Breakdown>,
+        public_author: Option<PublicAuthor>,
+        duration_seconds: Option<u64>,
+        draft: Option<bool>,
+        sct_auth: Option<QueryAuth>,
+        credentials: Option<Vec<ContractCredential>>,
+    },
+    // Publish one of the sender's drafts, making it a live poll
+    // Takes the same funds and creator credentials as MakePoll, and the poll's duration starts now
+    PublishPoll {
+        poll_id: String,
+        sct_auth: Option<QueryAuth>,
+        credentials: Option<Vec<ContractCredential>>,
+    },
+    // Edit a poll's title, description or options before anyone has voted (creator only)
+    // Unset fields are unchanged, the poll_id stays the same, and the old content is kept as a revision
+    // The creator's drafts can be edited too (without keeping revisions)
+    EditPoll {
+        poll_id: String,
+        title: Option<String>,
+        description: Option<String>,
+        options: Option<Vec<String>>,
+    },
+    // Archive a closed poll, replacing it with a compact final result (anyone)
+    // Removes up to limit of its votes, ballots, flags and revisions per call (default and max 100),
+    // so a poll with many votes is cleaned up over several calls
+    ArchivePoll {
+        poll_id: String,
+        limit: Option<u32>,
+    },
+    // Delete a poll that never got a vote, or a draft (creator only)
+    DeletePoll {
+        poll_id: String,
     },
     // Cast a vote on an existing poll by poll_id and option index
+    // The credentials needed depend on the poll's eligibility rule:
+    // - sct_viewing_key for the default SCT contract (and trait weights)
+    // - snip20_auth for SNIP-20 balance rules
+    // - merkle_proof for allowlist rules
+    // - credentials for any other SNIP-721 or SNIP-20 contract (overrides the above)
+    // Set hide_option to leave the option out of the response attributes
+    // revision is the poll's edits when the voter chose the option, so a vote sent before an edit
+    // fails instead of counting towards whatever option now has that index
     CastVote {
         poll_id: String,
+        revision: u32,
         option_idx: u32,
         sct_viewing_key: String,
+        snip20_auth: Option<QueryAuth>,
+        merkle_proof: Option<Vec<String>>,
+        credentials: Option<Vec<ContractCredential>>,
+        hide_option: Option<bool>,
+        // Ignored, lets clients pad the message so its size doesn't depend on the vote
+        padding: Option<String>,
+    },
+    // Answer every question of a survey in one ballot
+    // answers holds the chosen option indices for each question, in order
+    // Questions that weren't shown (see `QuestionCondition`) must have an empty answer
+    // Takes the same credentials as CastVote, and eligibility is checked once for the whole survey
+    SubmitBallot {
+        poll_id: String,
+        answers: Vec<Vec<u32>>,
+        sct_viewing_key: String,
+        snip20_auth: Option<QueryAuth>,
+        merkle_proof: Option<Vec<String>>,
+        credentials: Option<Vec<ContractCredential>>,
+        // Ignored, lets clients pad the message so its size doesn't depend on the answers
+        padding: Option<String>,
+    },
+    // Cast a vote through a relayer, who submits the transaction and pays the gas
+    // The voter is the signer of the ballot, not the sender, so their participation stays private
+    // sct_permit replaces sct_viewing_key and, like any permit in snip20_auth or credentials,
+    // must be signed by the voter
+    RelayVote {
+        ballot: Permit<Ballot>,
+        sct_permit: Permit,
+        snip20_auth: Option<QueryAuth>,
+        merkle_proof: Option<Vec<String>>,
+        credentials: Option<Vec<ContractCredential>>,
+        hide_option: Option<bool>,
+        padding: Option<String>,
+    },
+    // Flag a poll for moderation, once per poll, with a reason code (see `MAX_FLAG_REASON`)
+    // Only users meeting the poll's eligibility rules can flag it, with the same credentials as CastVote
+    // Polls with at least the config's flag_threshold flags are hidden from GetPolls
+    FlagPoll {
+        poll_id: String,
+        reason_code: u8,
+        sct_viewing_key: String,
+        snip20_auth: Option<QueryAuth>,
+        merkle_proof: Option<Vec<String>>,
+        credentials: Option<Vec<ContractCredential>>,
+        padding: Option<String>,
+    },
+    // Hide a poll from GetPolls, forfeiting its deposit (moderators and admin only)
+    // The reason is recorded in the public audit log
+    HidePoll {
+        poll_id: String,
+        reason: String,
+    },
+    // Show a poll in GetPolls again, even if it has been flagged (moderators and admin only)
+    // The reason is recorded in the public audit log
+    RestorePoll {
+        poll_id: String,
+        reason: String,
+    },
+    // Replace the list of moderators (admin only)
+    SetModerators {
+        moderators: Vec<Addr>,
+    },
+    // Register a verified organiser name for the given addresses, replacing any previous ones
+    // An empty list removes the organiser (admin only)
+    SetOrganiser {
+        name: String,
+        addresses: Vec<Addr>,
+    },
+    // Change the contract config, leaving unset fields unchanged (admin only)
+    SetConfig {
+        require_sct_to_create: Option<bool>,
+        creation_fee: Option<Uint128>,
+        creation_deposit: Option<Uint128>,
+        deposit_refund_turnout: Option<u32>,
+        max_polls_per_window: Option<u32>,
+        rate_limit_window: Option<u64>,
+        max_open_polls: Option<u32>,
+        flag_threshold: Option<u32>,
+    },
+    // Register a community (SNIP-721 contract) and override whether creating polls in it requires its SCT
+    // None removes the community, so its polls follow the contract config again (admin only)
+    SetCommunityConfig {
+        community: Addr,
+        code_hash: String,
+        require_sct_to_create: Option<bool>,
+    },
+    // Reclaim a poll's deposit once it reaches the refund turnout (depositor only)
+    ClaimDeposit {
+        poll_id: String,
+    },
+    // Send collected fees to the recipient (defaults to all fees, sent to the sender) (admin only)
+    WithdrawFees {
+        amount: Option<Uint128>,
+        recipient: Option<Addr>,
+    },
+    // Hand the admin role to another address (admin only)
+    ChangeAdmin {
+        admin: Addr,
+    },
+    // Revoke one of the sender's permits (or ballots) by name, e.g. if it leaked
+    RevokePermit {
+        permit_name: String,
     },
+    // Create a random viewing key for the sender, for clients that can't sign permits
+    // The key is returned in the response data
+    CreateViewingKey {
+        entropy: String,
+        padding: Option<String>,
+    },
+    // Set the sender's viewing key to a chosen key
+    SetViewingKey {
+        key: String,
+        padding: Option<String>,
+    },
+}
+
+
+// Response data of execute messages
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteAnswer {
+    CreateViewingKey {
+        key: String,
+    },
+}
+
+
+// Vote signed off-chain by the voter for RelayVote
+// It is signed like a query permit, with this contract in allowed_tokens and the ballot as the only permission
+// created_at is the poll's creation time, so the ballot can't be cast on a later poll with the same poll_id
+// (one recreated after the first was deleted or archived)
+// revision is the poll's edits when the voter signed it, as in CastVote
+// Submitting a ballot again fails as a double vote, so the nonce only makes each ballot's signature unique
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct Ballot {
+    pub poll_id: String,
+    pub created_at: Timestamp,
+    pub revision: u32,
+    pub option_idx: u32,
+    pub nonce: String,
+}
+
+
+// Survey question given when making a survey
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct Question {
+    pub question: String,
+    pub options: Vec<String>,
+    // Defaults to single choice
+    pub method: Option<VotingMethod>,
+    // Only show (and require) the question when an earlier answer matches (defaults to always shown)
+    pub condition: Option<QuestionCondition>,
+}
+
+
+// Public author chosen when making a poll
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PublicAuthor {
+    // Show the sender's address
+    Address {},
+    // Show a verified organiser name the sender is registered for
+    Organiser { name: String },
+}
+
+
+// Credentials the contract uses to query another contract on the sender's behalf
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryAuth {
+    ViewingKey { key: String },
+    // Must be signed by the voter
+    Permit { permit: Permit },
+}
+
+
+// Credentials for a specific contract named in an eligibility rule
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ContractCredential {
+    pub contract_address: Addr,
+    pub auth: QueryAuth,
 }
 
 
@@ -43,41 +299,209 @@ pub enum ExecuteMsg {
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    // Get all polls
-    GetPolls { },
+    // Get all polls, leaving out hidden polls unless include_hidden is set
+    GetPolls {
+        include_hidden: Option<bool>,
+    },
+    // Get a single poll by poll_id (hidden polls included)
+    GetPoll {
+        poll_id: String,
+    },
+    // Get a closed survey's answers to one question broken down by the answers to another
+    // The pair of questions must be one of the survey's cross_tabs (archived surveys included)
+    // Counts below the minimum group size are suppressed (see `MIN_GROUP_SIZE`)
+    GetCrossTab {
+        poll_id: String,
+        row_question: u32,
+        column_question: u32,
+    },
+    // Get a closed poll's votes broken down by its breakdown trait (archived polls included)
+    // Counts below the minimum group size are suppressed (see `MIN_GROUP_SIZE`)
+    GetBreakdown {
+        poll_id: String,
+    },
+    // Get the final result of an archived poll
+    GetPollResult {
+        poll_id: String,
+    },
+    // Get the previous contents of an edited poll, oldest first
+    GetPollRevisions {
+        poll_id: String,
+    },
+    // Get the number of flags for each reason code on a poll
+    GetPollFlags {
+        poll_id: String,
+    },
     // Get the tally for a specific poll by poll_id
     GetNumPolls { },
+    // Get the verified organisers and their addresses
+    GetOrganisers { },
+    // Get the contract config and its per-community overrides
+    GetConfig { },
+    // Get the fees and deposits held by the contract
+    GetTreasury { },
+    // Get the moderators
+    GetModerators { },
+    // Get the moderation audit log, oldest first (page defaults to 0, page_size to 50)
+    GetAuditLog {
+        page: Option<u32>,
+        page_size: Option<u32>,
+    },
 
     // Queries with permits
     WithPermit {
         // Permit used to verify querier identity
-        permit: Permit,
+        permit: Permit<PollPermissions>,
         // Query to perform
         query: QueryWithPermit,
     },
+
+    // Queries with viewing keys (same queries as with permits)
+    WithViewingKey {
+        address: Addr,
+        key: String,
+        query: QueryWithPermit,
+    },
+}
+
+// Permissions a permit can grant for this contract's queries
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PollPermissions {
+    // Read the permit signer's votes
+    Votes,
+    // Read the polls the permit signer created
+    Polls,
+    // Grants every permission
+    Owner,
 }
 
-// These are query messages that use permits
+// These are query messages that use permits or viewing keys
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryWithPermit {
-    // A request to get the user's vote on a specific poll
+    // A request to get the user's vote on a specific poll (needs the votes permission)
     GetMyVote { 
         poll_id: String,
     },
+    // A request to get the user's ballot on a survey (needs the votes permission)
+    GetMyBallot {
+        poll_id: String,
+    },
+    // A request to get the user's votes on several polls at once (max 50, needs the votes permission)
+    GetMyVotes {
+        poll_ids: Vec<String>,
+    },
+    // A request to page through every poll the user has voted on, oldest first (needs the votes permission)
+    GetMyVotedPolls {
+        page: Option<u32>,
+        page_size: Option<u32>,
+    },
+    // A request to page through the polls the user created, oldest first (needs the polls permission)
+    GetMyPolls {
+        page: Option<u32>,
+        page_size: Option<u32>,
+    },
+    // A request to get the user's unpublished drafts (needs the polls permission)
+    GetMyDrafts { },
+}
+
+// A verified organiser and the addresses that can post as them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Organiser {
+    pub name: String,
+    pub addresses: Vec<Addr>,
+}
+
+// A community's override of the contract config
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CommunityConfig {
+    pub community: Addr,
+    pub code_hash: String,
+    pub require_sct_to_create: bool,
+}
+
+// A user's vote on a poll, or their ballot on a survey (both None if they haven't taken part)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PollVote {
+    pub poll_id: String,
+    pub vote: Option<u32>,
+    pub answers: Option<Vec<Vec<u32>>>,
 }
 
 // Responses to queries
 #[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum QueryAnswer {
     GetPolls {
         polls: Vec<Poll>,
     },
+    GetPoll {
+        poll: Poll,
+        hidden: bool,
+    },
+    GetCrossTab {
+        // Number of respondents who answered both questions
+        respondents: u32,
+        // Respondents who chose each row question option (outer) and column question option (inner)
+        // None where the count is suppressed
+        cells: Vec<Vec<Option<u32>>>,
+    },
+    GetBreakdown {
+        voters: u32,
+        // Voters who chose each option (inner), for each breakdown value then "other" (outer)
+        // None where the count is suppressed
+        cells: Vec<Vec<Option<u32>>>,
+    },
+    GetPollResult {
+        result: PollResult,
+    },
+    GetPollRevisions {
+        revisions: Vec<PollRevision>,
+    },
+    GetPollFlags {
+        // Number of flags for each reason code (indexed by reason code)
+        reasons: Vec<u32>,
+    },
     GetNumPolls {
         num_polls: u32,
     },
+    GetOrganisers {
+        organisers: Vec<Organiser>,
+    },
+    GetConfig {
+        config: Config,
+        communities: Vec<CommunityConfig>,
+    },
+    GetTreasury {
+        treasury: Treasury,
+    },
+    GetModerators {
+        moderators: Vec<Addr>,
+    },
+    GetAuditLog {
+        entries: Vec<AuditEntry>,
+        total: u32,
+    },
     GetMyVote {
         vote: Option<u32>,
     },
+    GetMyBallot {
+        answers: Option<Vec<Vec<u32>>>,
+    },
+    GetMyVotes {
+        votes: Vec<PollVote>,
+    },
+    GetMyVotedPolls {
+        votes: Vec<PollVote>,
+        total: u32,
+    },
+    GetMyPolls {
+        polls: Vec<Poll>,
+        total: u32,
+    },
+    GetMyDrafts {
+        drafts: Vec<PollDraft>,
+    },
 }
@@ -8,9 +8,128 @@ See `polling-contract-design.md` for more details.
 
 // Imports
 use schemars::JsonSchema;
-use secret_toolkit::storage::{Item, Keymap};
+use secret_toolkit::storage::{AppendStore, Item, Keymap};
 use serde::{Deserialize, Serialize};
-use cosmwasm_std::{CanonicalAddr, Timestamp};
+use cosmwasm_std::{Addr, CanonicalAddr, Timestamp, Uint128};
+
+
+// Contract admin (manages the organiser registry)
+pub static ADMIN_KEY: &[u8] = b"admin";
+pub static ADMIN: Item<CanonicalAddr> = Item::new(ADMIN_KEY);
+
+
+// Contract settings the admin can change without redeploying
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct Config {
+    // Whether creating a poll requires holding an SCT of the poll's community
+    pub require_sct_to_create: bool,
+    // Non-refundable fee to create a poll, in uscrt (kept in the treasury)
+    pub creation_fee: Uint128,
+    // Refundable deposit to create a poll, in uscrt
+    pub creation_deposit: Uint128,
+    // Number of votes a poll needs before its creator can reclaim the deposit
+    pub deposit_refund_turnout: u32,
+    // Maximum number of polls one creator can make per rate limit window (0 means no limit)
+    pub max_polls_per_window: u32,
+    // Length of the rate limit window in seconds
+    pub rate_limit_window: u64,
+    // Maximum number of open polls one creator can have at once (0 means no limit)
+    pub max_open_polls: u32,
+    // Number of flags that hides a poll from GetPolls (0 means polls are never hidden by flags)
+    pub flag_threshold: u32,
+}
+pub static CONFIG_KEY: &[u8] = b"config";
+pub static CONFIG: Item<Config> = Item::new(CONFIG_KEY);
+
+
+// Funds held by the contract, in uscrt
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema, Default)]
+pub struct Treasury {
+    // Collected fees and forfeited deposits (the admin can withdraw these)
+    pub fees: Uint128,
+    // Deposits still held for their polls
+    pub deposits_held: Uint128,
+}
+pub static TREASURY_KEY: &[u8] = b"treasury";
+pub static TREASURY: Item<Treasury> = Item::new(TREASURY_KEY);
+
+
+// Deposit paid to create a poll
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct PollDeposit {
+    // The depositor's creator nullifier (see `POLL_CREATORS`), so deposits don't link creators to polls
+    pub depositor: String,
+    pub amount: Uint128,
+    // Turnout needed to reclaim the deposit (the config's value when the poll was created)
+    pub refund_turnout: u32,
+}
+
+// Deposits held for each poll (poll_id -> PollDeposit)
+pub static POLL_DEPOSITS_KEY: &[u8] = b"poll_deposits";
+pub static POLL_DEPOSITS: Keymap<String, PollDeposit> = Keymap::new(POLL_DEPOSITS_KEY);
+
+
+// A creator's recent poll creation, for rate limits and open poll quotas
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Default)]
+pub struct CreatorActivity {
+    // Start of the creator's current rate limit window
+    pub window_start: Timestamp,
+    // Polls created in the current window
+    pub polls_in_window: u32,
+    // The creator's open polls, as (poll_id, closing time)
+    // Only tracked while max_open_polls is set, when every poll must close
+    pub open_polls: Vec<(String, Timestamp)>,
+}
+
+// Activity of each creator, keyed by their creator nullifier (see `POLL_CREATORS`)
+pub static CREATOR_ACTIVITY_KEY: &[u8] = b"creator_activity";
+pub static CREATOR_ACTIVITY: Keymap<String, CreatorActivity> = Keymap::new(CREATOR_ACTIVITY_KEY);
+
+
+// Community registered by the admin, with its override of `Config.require_sct_to_create`
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct CommunityRule {
+    // Code hash of the community's SNIP-721 contract, used for its creator credential check
+    pub code_hash: String,
+    pub require_sct_to_create: bool,
+}
+
+// Registered communities, keyed by their SNIP-721 contract address
+pub static COMMUNITY_CREATION_RULES_KEY: &[u8] = b"community_creation_rules";
+pub static COMMUNITY_CREATION_RULES: Keymap<String, CommunityRule> = Keymap::new(COMMUNITY_CREATION_RULES_KEY);
+
+
+// Moderators, who can hide and restore polls (managed by the admin)
+pub static MODERATORS_KEY: &[u8] = b"moderators";
+pub static MODERATORS: Item<Vec<CanonicalAddr>> = Item::new(MODERATORS_KEY);
+
+
+// Moderation actions recorded in the audit log
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    Hide,
+    Restore,
+}
+
+// Entry in the moderation audit log
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct AuditEntry {
+    pub timestamp: Timestamp,
+    pub moderator: Addr,
+    pub action: ModerationAction,
+    pub poll_id: String,
+    pub reason: String,
+}
+
+// Append-only log of every moderation action, readable by anyone
+pub static AUDIT_LOG_KEY: &[u8] = b"audit_log";
+pub static AUDIT_LOG: AppendStore<AuditEntry> = AppendStore::new(AUDIT_LOG_KEY);
+
+
+// Registry of verified organiser names (e.g. "The Student Union") to the addresses that can post as them
+pub static ORGANISERS_KEY: &[u8] = b"organisers";
+pub static ORGANISERS: Keymap<String, Vec<CanonicalAddr>> = Keymap::new(ORGANISERS_KEY);
 
 
 // SCT contract related constants
@@ -20,6 +139,153 @@ pub static SCT_CODE_HASH_KEY: &[u8] = b"sct_code_hash";
 pub static SCT_CODE_HASH: Item<String> = Item::new(SCT_CODE_HASH_KEY);
 
 
+// Storage prefix for permits revoked with RevokePermit
+pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
+
+
+// Credential metadata attribute (SNIP-721 trait) that a voter's SCT must carry
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct TraitRequirement {
+    pub trait_type: String,
+    pub value: String,
+}
+
+
+// Voting weight given to SCTs whose `trait_type` attribute equals `value`
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct TraitWeight {
+    pub value: String,
+    pub weight: Uint128,
+}
+
+
+// Weighted voting mode: a vote adds the voter's weight to the tally instead of 1
+// A voter with several SCTs gets the highest weight among them
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct TraitWeights {
+    pub trait_type: String,
+    pub weights: Vec<TraitWeight>,
+    // Weight of SCTs without a matching attribute (0 means they cannot vote)
+    pub default_weight: Uint128,
+}
+
+
+// Demographic breakdown: results are also counted by the value of an SCT metadata attribute
+// (e.g. department), read from the voter's SCT at vote time
+// Voters whose SCTs carry none of the values are counted as "other"
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct Breakdown {
+    pub trait_type: String,
+    pub values: Vec<String>,
+}
+
+
+// SNIP-20 balance rule: voters must hold at least `min_balance` of the token
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct Snip20Requirement {
+    pub contract_address: Addr,
+    pub code_hash: String,
+    pub min_balance: Uint128,
+    // If true, a vote adds the voter's balance at vote time to the tally instead of 1
+    // Balances aren't snapshotted, so the same tokens can be counted again after they're transferred
+    // to another voter, and the tally is only as sound as the token's distribution
+    pub weight_by_balance: bool,
+}
+
+
+// SNIP-721 credential contract other than the default SCT contract
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct Snip721Contract {
+    pub address: Addr,
+    pub code_hash: String,
+}
+
+
+// Eligibility rule tree stored with each poll
+// Leaves check a single credential, and `all`/`any` combine rules with AND/OR
+// e.g. "member AND (board OR committee)"
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EligibilityRule {
+    // Holds a token from an SNIP-721 contract (None means the default SCT contract)
+    HoldsNft { contract: Option<Snip721Contract> },
+    // Holds a single token carrying all of the given metadata traits
+    HasTraits { contract: Option<Snip721Contract>, traits: Vec<TraitRequirement> },
+    // Is on a Merkle allowlist with the given hex root
+    OnAllowlist { root: String },
+    // Holds at least a minimum SNIP-20 balance
+    Snip20Balance { requirement: Snip20Requirement },
+    // Every rule must pass
+    All { rules: Vec<EligibilityRule> },
+    // At least one rule must pass
+    Any { rules: Vec<EligibilityRule> },
+}
+
+
+// Public author of a poll, if its creator opted in
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PollAuthor {
+    // The creator's own address
+    Address { address: Addr },
+    // A verified organiser from the registry (only its registered addresses can post as it)
+    Organiser { name: String },
+}
+
+
+// How a survey question is answered
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingMethod {
+    // Exactly one option
+    SingleChoice,
+    // Between 1 and max_choices different options, each counted in the tally
+    MultipleChoice { max_choices: u32 },
+}
+
+
+// Branching rule: a survey question is only shown when an earlier question's answer
+// includes any of the given options (e.g. "If you answered No to Q1, why?")
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct QuestionCondition {
+    // Index of the earlier question
+    pub question: u32,
+    pub options: Vec<u32>,
+}
+
+
+// A question in a survey, with its own options and tally
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct SurveyQuestion {
+    pub question: String,
+    pub options: Vec<String>,
+    pub method: VotingMethod,
+    // When the question is shown (None means always)
+    pub condition: Option<QuestionCondition>,
+    pub tally: Vec<Uint128>,
+    // Number of respondents who were shown the question (the denominator for its tally)
+    pub respondents: u32,
+}
+
+
+// A pair of survey questions to cross-tabulate: answers to row_question broken down by answers to column_question
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct CrossTab {
+    pub row_question: u32,
+    pub column_question: u32,
+}
+
+
+// Counts behind one of a survey's cross-tabs (see `CROSS_TAB_COUNTS`)
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Default)]
+pub struct CrossTabCounts {
+    // Number of respondents who answered both questions
+    pub respondents: u32,
+    // Number of respondents who chose each pair of options (row option, then column option)
+    pub counts: Vec<Vec<u32>>,
+}
+
+
 // Poll struct
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
 pub struct Poll {
@@ -28,7 +294,52 @@ pub struct Poll {
     pub description: String,
     pub created_at: Timestamp,
     pub options: Vec<String>,
-    pub tally: Vec<u32>
+    pub tally: Vec<Uint128>,
+    // Survey questions, all answered in one ballot (empty for single-question polls, which use options and tally)
+    pub questions: Vec<SurveyQuestion>,
+    // Pairs of survey questions whose answers are cross-tabulated (see `CROSS_TAB_COUNTS`)
+    pub cross_tabs: Vec<CrossTab>,
+    // Who may vote on the poll
+    pub eligibility: EligibilityRule,
+    // Weighted voting configuration (None means one vote per voter, unless weighted by SNIP-20 balance)
+    pub trait_weights: Option<TraitWeights>,
+    // Attribute to break the results down by (see `BREAKDOWN_COUNTS`)
+    pub breakdown: Option<Breakdown>,
+    // Public author (None means the poll is anonymous)
+    pub author: Option<PollAuthor>,
+    // When voting ends (None means the poll never closes)
+    pub closes_at: Option<Timestamp>,
+    // Number of times eligible voters have flagged the poll
+    pub flags: u32,
+    // A moderator's decision to hide or show the poll, which overrides flags (None if not moderated)
+    pub moderation: Option<ModerationAction>,
+    // Number of times the creator has edited the poll (see `POLL_REVISIONS`)
+    pub edits: u32,
+}
+
+
+// Unpublished poll, only visible to its creator
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct PollDraft {
+    pub poll: Poll,
+    // How long the poll stays open once published (None means it never closes)
+    pub duration_seconds: Option<u64>,
+}
+
+// Map of poll_id to draft, suffixed by the creator's nullifier (scope "creator")
+// Drafts aren't in POLLS, so they don't appear in GetPolls or count in POLL_COUNT
+pub static DRAFTS_KEY: &[u8] = b"drafts";
+pub static DRAFTS: Keymap<String, PollDraft> = Keymap::new(DRAFTS_KEY);
+
+
+// Content of a poll before an edit
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct PollRevision {
+    // When the content was replaced
+    pub edited_at: Timestamp,
+    pub title: String,
+    pub description: String,
+    pub options: Vec<String>,
 }
 
 
@@ -37,11 +348,110 @@ pub static POLLS_KEY: &[u8] = b"polls";
 pub static POLLS: Keymap<String, Poll> = Keymap::new(POLLS_KEY);
 
 
-// Number of polls
+// One of a survey's cross-tabs, kept in its final result once archived (as returned by GetCrossTab)
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct CrossTabResult {
+    pub row_question: u32,
+    pub column_question: u32,
+    pub respondents: u32,
+    // None where the count is suppressed
+    pub cells: Vec<Vec<Option<u32>>>,
+}
+
+
+// Final result of an archived poll, kept after the poll and its votes are removed
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct PollResult {
+    pub poll_id: String,
+    pub title: String,
+    pub description: String,
+    pub options: Vec<String>,
+    pub tally: Vec<Uint128>,
+    pub questions: Vec<SurveyQuestion>,
+    pub created_at: Timestamp,
+    pub closed_at: Timestamp,
+    // Number of voters
+    pub voters: u32,
+    // The breakdown's cells with small counts suppressed, as returned by GetBreakdown
+    // (None if the poll had no breakdown, or too few voters for one)
+    pub breakdown: Option<Vec<Vec<Option<u32>>>>,
+    // The survey's cross-tabs with small counts suppressed (leaving out any with too few respondents)
+    pub cross_tabs: Vec<CrossTabResult>,
+    // Whether all of the poll's votes, ballots, flags and revisions have been removed
+    pub cleaned_up: bool,
+}
+
+// Map of poll_id to the final result of archived polls
+pub static POLL_RESULTS_KEY: &[u8] = b"poll_results";
+pub static POLL_RESULTS: Keymap<String, PollResult> = Keymap::new(POLL_RESULTS_KEY);
+
+
+// Number of polls in POLLS (archived and deleted polls don't count)
 pub static POLL_COUNT_KEY: &[u8] = b"poll_count";
 pub static POLL_COUNT: Item<u32> = Item::new(POLL_COUNT_KEY);
 
 
-// Map of (poll_id + hashed_voter_address) to their vote (option_idx)
+// Secret key for voter nullifiers, created from block randomness at instantiation
+pub static NULLIFIER_SECRET_KEY: &[u8] = b"nullifier_secret";
+pub static NULLIFIER_SECRET: Item<Vec<u8>> = Item::new(NULLIFIER_SECRET_KEY);
+
+
+// Map of voter nullifier to their vote (option_idx), suffixed by poll_id
+// A nullifier is the voter's address hashed with NULLIFIER_SECRET and the poll_id
 pub static VOTES_KEY: &[u8] = b"voted";
 pub static VOTES: Keymap<String, u32> = Keymap::new(VOTES_KEY);
+
+
+// Map of poll_id to its breakdown counts: the number of voters in each breakdown group (each value,
+// then "other") who chose each option
+// Kept out of the public Poll, and only readable through GetBreakdown with small counts suppressed
+pub static BREAKDOWN_COUNTS_KEY: &[u8] = b"breakdown_counts";
+pub static BREAKDOWN_COUNTS: Keymap<String, Vec<Vec<u32>>> = Keymap::new(BREAKDOWN_COUNTS_KEY);
+
+
+// Map of voter nullifier to their survey ballot (the chosen option indices for each question), suffixed by poll_id
+// This is a survey respondent's only participation record, so each respondent is counted once
+pub static SURVEY_BALLOTS_KEY: &[u8] = b"survey_ballots";
+pub static SURVEY_BALLOTS: Keymap<String, Vec<Vec<u32>>> = Keymap::new(SURVEY_BALLOTS_KEY);
+
+
+// Map of poll_id to the counts behind each of a survey's cross-tabs (in the order of Poll.cross_tabs)
+// Updated with each ballot, and only readable through GetCrossTab with small counts suppressed
+pub static CROSS_TAB_COUNTS_KEY: &[u8] = b"cross_tab_counts";
+pub static CROSS_TAB_COUNTS: Keymap<String, Vec<CrossTabCounts>> = Keymap::new(CROSS_TAB_COUNTS_KEY);
+
+
+// Map of flagger nullifier to their flag's reason code, suffixed by poll_id
+// The nullifier uses the scope "flag_" + poll_id, so flags can't be linked to votes
+pub static FLAGS_KEY: &[u8] = b"flags";
+pub static FLAGS: Keymap<String, u8> = Keymap::new(FLAGS_KEY);
+
+
+// List of the poll_ids a voter has voted on, suffixed by the voter's nullifier (scope "voted_polls")
+// Only the voter can read it, through an authenticated query
+pub static VOTED_POLLS_KEY: &[u8] = b"voted_polls";
+pub static VOTED_POLLS: AppendStore<String> = AppendStore::new(VOTED_POLLS_KEY);
+
+
+// List of each poll's previous contents, oldest first, suffixed by poll_id
+pub static POLL_REVISIONS_KEY: &[u8] = b"poll_revisions";
+pub static POLL_REVISIONS: AppendStore<PollRevision> = AppendStore::new(POLL_REVISIONS_KEY);
+
+
+// Map of the content ID (see generate_content_id) of an edited poll's current content to its poll_id
+// An unedited poll's content ID is its poll_id, so only edited polls need an entry
+// Checked alongside POLLS so two live polls can't have the same title and options
+pub static EDITED_CONTENT_KEY: &[u8] = b"edited_content";
+pub static EDITED_CONTENT: Keymap<String, String> = Keymap::new(EDITED_CONTENT_KEY);
+
+
+// Map of poll_id to its creator's nullifier (scope "creator")
+// Lets the contract check who created a poll without storing their address
+pub static POLL_CREATORS_KEY: &[u8] = b"poll_creators";
+pub static POLL_CREATORS: Keymap<String, String> = Keymap::new(POLL_CREATORS_KEY);
+
+
+// List of the poll_ids a user has created, suffixed by their nullifier (scope "creator")
+// Only the creator can read it, through an authenticated query
+pub static CREATED_POLLS_KEY: &[u8] = b"created_polls";
+pub static CREATED_POLLS: AppendStore<String> = AppendStore::new(CREATED_POLLS_KEY);
@@ -2,15 +2,18 @@ use cosmwasm_std::testing::{
     mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
 };
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, ContractResult, OwnedDeps,
+    coins, from_binary, to_binary, Addr, BankMsg, Binary, ContractResult, CosmosMsg, OwnedDeps, Uint128,
     SystemError, SystemResult, WasmQuery,
 };
+use secret_toolkit::permit::{Permissions, Permit, PermitParams, PermitSignature, PubKey, SignedPermit, TokenPermissions};
+use secp256k1::{Message, Secp256k1, SecretKey};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use hex;
 
 use polling_contract::contract::{execute, instantiate, query};
-use polling_contract::msg::{ExecuteMsg, InstantiateMsg, QueryAnswer, QueryMsg};
+use polling_contract::msg::{Ballot, CommunityConfig, ContractCredential, ExecuteAnswer, ExecuteMsg, InstantiateMsg, Organiser, PollPermissions, PollVote, QueryAnswer, QueryAuth, QueryMsg, QueryWithPermit, PublicAuthor, Question};
+use polling_contract::state::{Breakdown, CrossTab, EligibilityRule, ModerationAction, PollAuthor, POLLS, Snip20Requirement, Snip721Contract, QuestionCondition, TraitRequirement, TraitWeight, TraitWeights, VotingMethod};
 
 // Mock structures for SCT contract responses
 #[derive(Serialize, Deserialize)]
@@ -35,6 +38,107 @@ struct TokenList {
     tokens: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize)]
+struct NftDossierQuery {
+    nft_dossier: NftDossierQueryParams,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NftDossierQueryParams {
+    token_id: String,
+    viewer: ViewerInfo,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ViewerInfo {
+    address: String,
+    viewing_key: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snip721PermitQuery {
+    with_permit: Snip721PermitQueryParams,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snip721PermitQueryParams {
+    permit: Permit,
+    query: Snip721QueryWithPermit,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Snip721QueryWithPermit {
+    Tokens { owner: String, limit: Option<u32> },
+    NftDossier { token_id: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct NftDossierResponse {
+    nft_dossier: NftDossier,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NftDossier {
+    public_metadata: Option<Metadata>,
+    private_metadata: Option<Metadata>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Metadata {
+    extension: Option<Extension>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Extension {
+    attributes: Option<Vec<Trait>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Trait {
+    trait_type: Option<String>,
+    value: String,
+}
+
+// Mock structures for SNIP-20 token contract responses
+#[derive(Serialize, Deserialize)]
+struct Snip20BalanceQuery {
+    balance: Snip20BalanceQueryParams,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snip20BalanceQueryParams {
+    address: String,
+    key: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snip20BalanceResponse {
+    balance: Snip20Balance,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snip20Balance {
+    amount: Uint128,
+}
+
+// Mock SCT metadata: every token privately holds the traits role=faculty and region=NZ
+fn mock_nft_dossier_response() -> NftDossierResponse {
+    NftDossierResponse {
+        nft_dossier: NftDossier {
+            public_metadata: None,
+            private_metadata: Some(Metadata {
+                extension: Some(Extension {
+                    attributes: Some(vec![
+                        Trait { trait_type: Some("role".to_string()), value: "faculty".to_string() },
+                        Trait { trait_type: Some("region".to_string()), value: "NZ".to_string() },
+                    ]),
+                }),
+            }),
+        },
+    }
+}
+
 // Helper function to create mock dependencies with SCT contract mocking
 fn mock_dependencies_with_sct() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
     let mut deps = mock_dependencies();
@@ -44,6 +148,31 @@ fn mock_dependencies_with_sct() -> OwnedDeps<MockStorage, MockApi, MockQuerier>
         match query {
             WasmQuery::Smart { contract_addr, msg, .. } => {
                 if contract_addr == "secret1sctcontract" {
+                    // Mock permit queries: any permit named "sct_permit" is accepted (signatures aren't checked here)
+                    if let Ok(permit_query) = from_binary::<Snip721PermitQuery>(msg) {
+                        if permit_query.with_permit.permit.params.permit_name != "sct_permit" {
+                            return SystemResult::Ok(ContractResult::Err("Query failed".to_string()));
+                        }
+                        return match permit_query.with_permit.query {
+                            Snip721QueryWithPermit::Tokens { .. } => {
+                                let response = TokensResponse {
+                                    token_list: TokenList { tokens: vec!["token1".to_string()] },
+                                };
+                                SystemResult::Ok(ContractResult::Ok(to_binary(&response).unwrap()))
+                            }
+                            Snip721QueryWithPermit::NftDossier { .. } => {
+                                SystemResult::Ok(ContractResult::Ok(to_binary(&mock_nft_dossier_response()).unwrap()))
+                            }
+                        };
+                    }
+                    // Mock metadata response: only readable with "valid_key"
+                    if let Ok(dossier_query) = from_binary::<NftDossierQuery>(msg) {
+                        return if dossier_query.nft_dossier.viewer.viewing_key == "valid_key" {
+                            SystemResult::Ok(ContractResult::Ok(to_binary(&mock_nft_dossier_response()).unwrap()))
+                        } else {
+                            SystemResult::Ok(ContractResult::Err("Query failed".to_string()))
+                        };
+                    }
                     let query: Result<TokensQuery, _> = from_binary(msg);
                     if let Ok(tokens_query) = query {
                         // Mock response: if viewing key is "valid_key", return tokens
@@ -64,6 +193,17 @@ fn mock_dependencies_with_sct() -> OwnedDeps<MockStorage, MockApi, MockQuerier>
                             request: msg.clone(),
                         })
                     }
+                } else if contract_addr == "secret1snip20token" {
+                    // Mock response: if viewing key is "valid_key", the balance is 250
+                    match from_binary::<Snip20BalanceQuery>(msg) {
+                        Ok(balance_query) if balance_query.balance.key == "valid_key" => {
+                            let response = Snip20BalanceResponse {
+                                balance: Snip20Balance { amount: Uint128::new(250) },
+                            };
+                            SystemResult::Ok(ContractResult::Ok(to_binary(&response).unwrap()))
+                        }
+                        _ => SystemResult::Ok(ContractResult::Err("Query failed".to_string())),
+                    }
                 } else {
                     SystemResult::Err(SystemError::NoSuchContract { addr: contract_addr.clone() })
                 }
@@ -82,9 +222,53 @@ fn get_instantiate_msg() -> InstantiateMsg {
     InstantiateMsg {
         sct_contract_address: Addr::unchecked("secret1sctcontract"),
         sct_code_hash: "sct_code_hash".to_string(),
+        admin: None,
+        require_sct_to_create: None,
+        creation_fee: None,
+        creation_deposit: None,
+        deposit_refund_turnout: None,
+        max_polls_per_window: None,
+        rate_limit_window: None,
+        max_open_polls: None,
+        flag_threshold: None,
+    }
+}
+
+// Helper function to create a MakePoll message with no optional settings
+fn new_poll_msg(title: String, description: String, options: Vec<String>) -> ExecuteMsg {
+    ExecuteMsg::MakePoll {
+        title,
+        description,
+        options,
+        questions: None,
+        cross_tabs: None,
+        eligibility: None,
+        trait_weights: None,
+        breakdown: None,
+        public_author: None,
+        duration_seconds: None,
+        draft: None,
+        sct_auth: None,
+        credentials: None,
+    }
+}
+
+// Helper function to create a CastVote message with an SCT viewing key only
+fn new_vote_msg(poll_id: String, option_idx: u32, sct_viewing_key: String) -> ExecuteMsg {
+    ExecuteMsg::CastVote {
+        poll_id,
+        revision: 0,
+        option_idx,
+        sct_viewing_key,
+        snip20_auth: None,
+        merkle_proof: None,
+        credentials: None,
+        hide_option: None,
+        padding: None,
     }
 }
 
+
 // Helper function to generate poll ID (same logic as in contract)
 fn generate_poll_id(title: &str, options: &[String]) -> String {
     let mut hasher = Sha256::new();
@@ -133,11 +317,11 @@ fn test_make_poll_success() {
     instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
     
     // Create a poll
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Test Poll".to_string(),
-        description: "This is a test poll".to_string(),
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "Test Poll".to_string(),
+        "This is a test poll".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     
     let result = execute(deps.as_mut(), env, info, make_poll_msg);
     assert!(result.is_ok());
@@ -166,26 +350,26 @@ fn test_make_poll_invalid_options_count() {
     instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
     
     // Try to create poll with only one option (should fail)
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Bad Poll".to_string(),
-        description: "This poll has too few options".to_string(),
-        options: vec!["Only Option".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "Bad Poll".to_string(),
+        "This poll has too few options".to_string(),
+        vec!["Only Option".to_string()],
+    );
     
     let result = execute(deps.as_mut(), env.clone(), info.clone(), make_poll_msg);
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("Invalid number of options"));
     
     // Try to create poll with too many options (should fail)
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Another Bad Poll".to_string(),
-        description: "This poll has too many options".to_string(),
-        options: vec![
+    let make_poll_msg = new_poll_msg(
+        "Another Bad Poll".to_string(),
+        "This poll has too many options".to_string(),
+        vec![
             "Option 1".to_string(), "Option 2".to_string(), "Option 3".to_string(),
             "Option 4".to_string(), "Option 5".to_string(), "Option 6".to_string(),
             "Option 7".to_string(), "Option 8".to_string(), "Option 9".to_string(),
         ],
-    };
+    );
     
     let result = execute(deps.as_mut(), env, info, make_poll_msg);
     assert!(result.is_err());
@@ -203,22 +387,22 @@ fn test_make_poll_empty_title_or_description() {
     instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
     
     // Try to create poll with empty title
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "".to_string(),
-        description: "Valid description".to_string(),
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "".to_string(),
+        "Valid description".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     
     let result = execute(deps.as_mut(), env.clone(), info.clone(), make_poll_msg);
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("Title and description cannot be empty"));
     
     // Try to create poll with empty description
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Valid title".to_string(),
-        description: "".to_string(),
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "Valid title".to_string(),
+        "".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     
     let result = execute(deps.as_mut(), env, info, make_poll_msg);
     assert!(result.is_err());
@@ -238,11 +422,11 @@ fn test_make_poll_title_too_long() {
     // Create a title longer than 100 characters
     let long_title = "a".repeat(101);
     
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: long_title,
-        description: "Valid description".to_string(),
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        long_title,
+        "Valid description".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     
     let result = execute(deps.as_mut(), env, info, make_poll_msg);
     assert!(result.is_err());
@@ -262,11 +446,11 @@ fn test_make_poll_description_too_long() {
     // Create a description longer than 500 characters
     let long_description = "a".repeat(501);
     
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Valid title".to_string(),
-        description: long_description,
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "Valid title".to_string(),
+        long_description,
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     
     let result = execute(deps.as_mut(), env, info, make_poll_msg);
     assert!(result.is_err());
@@ -284,11 +468,11 @@ fn test_make_duplicate_poll() {
     instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
     
     // Create first poll
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Duplicate Poll".to_string(),
-        description: "This is a test poll".to_string(),
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "Duplicate Poll".to_string(),
+        "This is a test poll".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     
     let result = execute(deps.as_mut(), env.clone(), info.clone(), make_poll_msg.clone());
     assert!(result.is_ok());
@@ -310,22 +494,22 @@ fn test_cast_vote_success() {
     instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
     
     // Create a poll
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Test Poll".to_string(),
-        description: "This is a test poll".to_string(),
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "Test Poll".to_string(),
+        "This is a test poll".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
     
     // Generate the poll ID directly (same logic as in contract)
     let poll_id = generate_poll_id("Test Poll", &vec!["Option A".to_string(), "Option B".to_string()]);
     
     // Cast vote with valid SCT
-    let vote_msg = ExecuteMsg::CastVote {
-        poll_id: poll_id.clone(),
-        option_idx: 0,
-        sct_viewing_key: "valid_key".to_string(),
-    };
+    let vote_msg = new_vote_msg(
+        poll_id.clone(),
+        0,
+        "valid_key".to_string(),
+    );
     
     let result = execute(deps.as_mut(), env, info, vote_msg);
     assert!(result.is_ok());
@@ -336,6 +520,33 @@ fn test_cast_vote_success() {
     // indicates the vote was recorded correctly.
 }
 
+#[test]
+fn test_cast_vote_response_padding() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Test Poll", &options);
+    
+    // Cast a vote that leaves the option out of the response
+    let mut vote_msg = new_vote_msg(poll_id, 1, "valid_key".to_string());
+    if let ExecuteMsg::CastVote { hide_option, .. } = &mut vote_msg {
+        *hide_option = Some(true);
+    }
+    let response = execute(deps.as_mut(), env, mock_info("voter", &[]), vote_msg).unwrap();
+    
+    // Attributes are padded to the block size and don't mention the option
+    assert!(response.attributes.iter().all(|attribute| attribute.key.len() % 256 == 0 && attribute.value.len() % 256 == 0));
+    assert!(response.attributes.iter().all(|attribute| attribute.key.trim_end() != "option_idx"));
+}
+
 #[test]
 fn test_cast_vote_without_sct() {
     let mut deps = mock_dependencies_with_sct();
@@ -347,22 +558,22 @@ fn test_cast_vote_without_sct() {
     instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
     
     // Create a poll
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Test Poll".to_string(),
-        description: "This is a test poll".to_string(),
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "Test Poll".to_string(),
+        "This is a test poll".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
     
     // Generate the poll ID directly (same logic as in contract)
     let poll_id = generate_poll_id("Test Poll", &vec!["Option A".to_string(), "Option B".to_string()]);
     
     // Try to cast vote without valid SCT
-    let vote_msg = ExecuteMsg::CastVote {
+    let vote_msg = new_vote_msg(
         poll_id,
-        option_idx: 0,
-        sct_viewing_key: "invalid_key".to_string(),
-    };
+        0,
+        "invalid_key".to_string(),
+    );
     
     let result = execute(deps.as_mut(), env, info, vote_msg);
     assert!(result.is_err());
@@ -380,32 +591,32 @@ fn test_cast_vote_double_voting() {
     instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
     
     // Create a poll
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Test Poll".to_string(),
-        description: "This is a test poll".to_string(),
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "Test Poll".to_string(),
+        "This is a test poll".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
     
     // Generate the poll ID directly (same logic as in contract)
     let poll_id = generate_poll_id("Test Poll", &vec!["Option A".to_string(), "Option B".to_string()]);
     
     // Cast first vote
-    let vote_msg = ExecuteMsg::CastVote {
-        poll_id: poll_id.clone(),
-        option_idx: 0,
-        sct_viewing_key: "valid_key".to_string(),
-    };
+    let vote_msg = new_vote_msg(
+        poll_id.clone(),
+        0,
+        "valid_key".to_string(),
+    );
     
     let result = execute(deps.as_mut(), env.clone(), info.clone(), vote_msg);
     assert!(result.is_ok());
     
     // Try to cast second vote (should fail)
-    let vote_msg = ExecuteMsg::CastVote {
+    let vote_msg = new_vote_msg(
         poll_id,
-        option_idx: 1,
-        sct_viewing_key: "valid_key".to_string(),
-    };
+        1,
+        "valid_key".to_string(),
+    );
     
     let result = execute(deps.as_mut(), env, info, vote_msg);
     assert!(result.is_err());
@@ -423,11 +634,11 @@ fn test_cast_vote_invalid_poll_id() {
     instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
     
     // Try to vote on non-existent poll
-    let vote_msg = ExecuteMsg::CastVote {
-        poll_id: "nonexistent_poll_id".to_string(),
-        option_idx: 0,
-        sct_viewing_key: "valid_key".to_string(),
-    };
+    let vote_msg = new_vote_msg(
+        "nonexistent_poll_id".to_string(),
+        0,
+        "valid_key".to_string(),
+    );
     
     let result = execute(deps.as_mut(), env, info, vote_msg);
     assert!(result.is_err());
@@ -445,22 +656,22 @@ fn test_cast_vote_invalid_option_index() {
     instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
     
     // Create a poll
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Test Poll".to_string(),
-        description: "This is a test poll".to_string(),
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "Test Poll".to_string(),
+        "This is a test poll".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
     
     // Generate the poll ID directly (same logic as in contract)
     let poll_id = generate_poll_id("Test Poll", &vec!["Option A".to_string(), "Option B".to_string()]);
     
     // Try to vote with invalid option index (poll only has 2 options: 0, 1)
-    let vote_msg = ExecuteMsg::CastVote {
+    let vote_msg = new_vote_msg(
         poll_id,
-        option_idx: 2,
-        sct_viewing_key: "valid_key".to_string(),
-    };
+        2,
+        "valid_key".to_string(),
+    );
     
     let result = execute(deps.as_mut(), env, info, vote_msg);
     assert!(result.is_err());
@@ -483,11 +694,11 @@ fn test_query_get_polls() {
     ];
     
     for (title, description, options) in polls_data {
-        let make_poll_msg = ExecuteMsg::MakePoll {
-            title: title.to_string(),
-            description: description.to_string(),
-            options: options.iter().map(|s| s.to_string()).collect(),
-        };
+        let make_poll_msg = new_poll_msg(
+            title.to_string(),
+            description.to_string(),
+            options.iter().map(|s| s.to_string()).collect(),
+        );
         execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
     }
     
@@ -530,11 +741,11 @@ fn test_query_get_num_polls() {
     }
     
     // Create a poll
-    let make_poll_msg = ExecuteMsg::MakePoll {
-        title: "Test Poll".to_string(),
-        description: "This is a test poll".to_string(),
-        options: vec!["Option A".to_string(), "Option B".to_string()],
-    };
+    let make_poll_msg = new_poll_msg(
+        "Test Poll".to_string(),
+        "This is a test poll".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
     execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
     
     // Check updated count
@@ -548,4 +759,1956 @@ fn test_query_get_num_polls() {
         }
         _ => panic!("Unexpected query response"),
     }
-} 
\ No newline at end of file
+} 
+
+// Helper function to create an eligibility rule requiring the given SCT traits
+fn traits_rule(traits: Vec<TraitRequirement>) -> EligibilityRule {
+    EligibilityRule::HasTraits { contract: None, traits }
+}
+
+#[test]
+fn test_cast_vote_with_required_traits() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll for faculty in NZ only
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Faculty Poll".to_string(), "Faculty only".to_string(), options.clone());
+    if let ExecuteMsg::MakePoll { eligibility, .. } = &mut make_poll_msg {
+        *eligibility = Some(traits_rule(vec![
+            TraitRequirement { trait_type: "role".to_string(), value: "faculty".to_string() },
+            TraitRequirement { trait_type: "region".to_string(), value: "NZ".to_string() },
+        ]));
+    }
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Faculty Poll", &options);
+    
+    // Voter's SCT carries both traits, so the vote is accepted
+    let vote_msg = new_vote_msg(
+        poll_id,
+        1,
+        "valid_key".to_string(),
+    );
+    let result = execute(deps.as_mut(), env, mock_info("voter", &[]), vote_msg);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_cast_vote_missing_required_trait() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll for students only
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Student Poll".to_string(), "Students only".to_string(), options.clone());
+    if let ExecuteMsg::MakePoll { eligibility, .. } = &mut make_poll_msg {
+        *eligibility = Some(traits_rule(vec![
+            TraitRequirement { trait_type: "role".to_string(), value: "student".to_string() },
+        ]));
+    }
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Student Poll", &options);
+    
+    // Voter holds an SCT, but not one with role=student
+    let vote_msg = new_vote_msg(
+        poll_id,
+        0,
+        "valid_key".to_string(),
+    );
+    let result = execute(deps.as_mut(), env, mock_info("voter", &[]), vote_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("No SCT with the required traits"));
+}
+
+#[test]
+fn test_make_poll_invalid_required_traits() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
+    
+    // Try to create poll with an empty trait value (should fail)
+    let mut make_poll_msg = new_poll_msg(
+        "Test Poll".to_string(),
+        "This is a test poll".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
+    if let ExecuteMsg::MakePoll { eligibility, .. } = &mut make_poll_msg {
+        *eligibility = Some(traits_rule(vec![
+            TraitRequirement { trait_type: "role".to_string(), value: "".to_string() },
+        ]));
+    }
+    let result = execute(deps.as_mut(), env, info, make_poll_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Required trait type and value cannot be empty"));
+}
+
+#[test]
+fn test_cast_vote_weighted_by_trait() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a stakeholder poll where faculty votes count 5 times
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Stakeholder Poll".to_string(), "Weighted by role".to_string(), options.clone());
+    if let ExecuteMsg::MakePoll { trait_weights, .. } = &mut make_poll_msg {
+        *trait_weights = Some(TraitWeights {
+            trait_type: "role".to_string(),
+            weights: vec![
+                TraitWeight { value: "student".to_string(), weight: Uint128::new(1) },
+                TraitWeight { value: "faculty".to_string(), weight: Uint128::new(5) },
+            ],
+            default_weight: Uint128::zero(),
+        });
+    }
+    
+    // Weights are read from the default SCT, so voters must be required to hold one
+    let other_nft = EligibilityRule::HoldsNft {
+        contract: Some(Snip721Contract {
+            address: Addr::unchecked("secret1othernft"),
+            code_hash: "other_nft_hash".to_string(),
+        }),
+    };
+    let mut other_nft_poll_msg = make_poll_msg.clone();
+    if let ExecuteMsg::MakePoll { title, eligibility, .. } = &mut other_nft_poll_msg {
+        *title = "Members Poll".to_string();
+        *eligibility = Some(EligibilityRule::Any {
+            rules: vec![EligibilityRule::HoldsNft { contract: None }, other_nft.clone()],
+        });
+    }
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), other_nft_poll_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("must require the default SCT"));
+    if let ExecuteMsg::MakePoll { eligibility, .. } = &mut other_nft_poll_msg {
+        *eligibility = Some(EligibilityRule::All {
+            rules: vec![EligibilityRule::HoldsNft { contract: None }, other_nft],
+        });
+    }
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), other_nft_poll_msg);
+    assert!(result.is_ok());
+    
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Stakeholder Poll", &options);
+    
+    // Voter's SCT has role=faculty
+    let vote_msg = new_vote_msg(
+        poll_id,
+        1,
+        "valid_key".to_string(),
+    );
+    let result = execute(deps.as_mut(), env, mock_info("voter", &[]), vote_msg);
+    assert!(result.is_ok());
+    
+    // Note: Due to secret-toolkit keymap iterator limitations in tests,
+    // we can't reliably query the weighted tally through GetPolls
+}
+
+#[test]
+fn test_cast_vote_without_voting_weight() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll where only board members carry weight
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Board Poll".to_string(), "Weighted by role".to_string(), options.clone());
+    if let ExecuteMsg::MakePoll { trait_weights, .. } = &mut make_poll_msg {
+        *trait_weights = Some(TraitWeights {
+            trait_type: "role".to_string(),
+            weights: vec![TraitWeight { value: "board".to_string(), weight: Uint128::new(3) }],
+            default_weight: Uint128::zero(),
+        });
+    }
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Board Poll", &options);
+    
+    // Voter's SCT has role=faculty, which falls back to the zero default weight
+    let vote_msg = new_vote_msg(
+        poll_id,
+        0,
+        "valid_key".to_string(),
+    );
+    let result = execute(deps.as_mut(), env, mock_info("voter", &[]), vote_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("no voting weight"));
+}
+
+// Helper function to create a token poll gated by the mock SNIP-20 contract
+fn new_token_poll_msg(title: &str, min_balance: u128, weight_by_balance: bool) -> ExecuteMsg {
+    let mut make_poll_msg = new_poll_msg(
+        title.to_string(),
+        "Token holders only".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
+    if let ExecuteMsg::MakePoll { eligibility, .. } = &mut make_poll_msg {
+        *eligibility = Some(EligibilityRule::Snip20Balance {
+            requirement: Snip20Requirement {
+                contract_address: Addr::unchecked("secret1snip20token"),
+                code_hash: "snip20_code_hash".to_string(),
+                min_balance: Uint128::new(min_balance),
+                weight_by_balance,
+            },
+        });
+    }
+    make_poll_msg
+}
+
+#[test]
+fn test_cast_vote_on_token_poll() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a balance-weighted token poll
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), new_token_poll_msg("Token Poll", 100, true)).unwrap();
+    let poll_id = generate_poll_id("Token Poll", &["Option A".to_string(), "Option B".to_string()]);
+    
+    // Voting without token credentials fails, even with a valid SCT viewing key
+    let vote_msg = new_vote_msg(poll_id.clone(), 0, "valid_key".to_string());
+    let result = execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), vote_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("snip20_auth is required"));
+    
+    // Voter holds 250 tokens, which is above the minimum of 100
+    let vote_msg = ExecuteMsg::CastVote {
+        poll_id,
+        revision: 0,
+        option_idx: 0,
+        sct_viewing_key: "".to_string(),
+        snip20_auth: Some(QueryAuth::ViewingKey { key: "valid_key".to_string() }),
+        merkle_proof: None,
+        credentials: None,
+        hide_option: None,
+        padding: None,
+    };
+    let result = execute(deps.as_mut(), env, mock_info("voter", &[]), vote_msg);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_cast_vote_token_balance_too_low() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a token poll that needs 1000 tokens
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), new_token_poll_msg("Whale Poll", 1000, false)).unwrap();
+    let poll_id = generate_poll_id("Whale Poll", &["Option A".to_string(), "Option B".to_string()]);
+    
+    // Voter only holds 250 tokens
+    let vote_msg = ExecuteMsg::CastVote {
+        poll_id,
+        revision: 0,
+        option_idx: 1,
+        sct_viewing_key: "".to_string(),
+        snip20_auth: Some(QueryAuth::ViewingKey { key: "valid_key".to_string() }),
+        merkle_proof: None,
+        credentials: None,
+        hide_option: None,
+        padding: None,
+    };
+    let result = execute(deps.as_mut(), env, mock_info("voter", &[]), vote_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Token balance below the poll's minimum"));
+}
+
+// Helper function to hash two Merkle nodes in sorted order (same logic as in contract)
+fn hash_merkle_pair(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    if a <= b {
+        hasher.update(a);
+        hasher.update(b);
+    } else {
+        hasher.update(b);
+        hasher.update(a);
+    }
+    hasher.finalize().to_vec()
+}
+
+// Helper function to create an allowlist poll for "voter" and three other addresses
+// Returns the poll message and the Merkle proof for "voter"
+fn new_allowlist_poll_msg(title: &str) -> (ExecuteMsg, Vec<String>) {
+    let leaves: Vec<Vec<u8>> = ["voter", "attendee1", "attendee2", "attendee3"]
+        .iter()
+        .map(|address| Sha256::digest([&[0x00], address.as_bytes()].concat()).to_vec())
+        .collect();
+    let left = hash_merkle_pair(&leaves[0], &leaves[1]);
+    let right = hash_merkle_pair(&leaves[2], &leaves[3]);
+    let root = hash_merkle_pair(&left, &right);
+    
+    let mut make_poll_msg = new_poll_msg(
+        title.to_string(),
+        "Conference attendees only".to_string(),
+        vec!["Option A".to_string(), "Option B".to_string()],
+    );
+    if let ExecuteMsg::MakePoll { eligibility, .. } = &mut make_poll_msg {
+        *eligibility = Some(EligibilityRule::OnAllowlist { root: hex::encode(root) });
+    }
+    (make_poll_msg, vec![hex::encode(&leaves[1]), hex::encode(right)])
+}
+
+#[test]
+fn test_cast_vote_on_allowlist_poll() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create an allowlist poll
+    let (make_poll_msg, proof) = new_allowlist_poll_msg("Attendee Poll");
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Attendee Poll", &["Option A".to_string(), "Option B".to_string()]);
+    
+    // Voter is on the allowlist and doesn't need an SCT
+    let vote_msg = ExecuteMsg::CastVote {
+        poll_id,
+        revision: 0,
+        option_idx: 0,
+        sct_viewing_key: "".to_string(),
+        snip20_auth: None,
+        merkle_proof: Some(proof),
+        credentials: None,
+        hide_option: None,
+        padding: None,
+    };
+    let result = execute(deps.as_mut(), env, mock_info("voter", &[]), vote_msg);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_cast_vote_not_on_allowlist() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create an allowlist poll
+    let (make_poll_msg, proof) = new_allowlist_poll_msg("Attendee Poll");
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Attendee Poll", &["Option A".to_string(), "Option B".to_string()]);
+    
+    // Someone else can't reuse the voter's proof
+    let vote_msg = ExecuteMsg::CastVote {
+        poll_id,
+        revision: 0,
+        option_idx: 0,
+        sct_viewing_key: "valid_key".to_string(),
+        snip20_auth: None,
+        merkle_proof: Some(proof),
+        credentials: None,
+        hide_option: None,
+        padding: None,
+    };
+    let result = execute(deps.as_mut(), env, mock_info("outsider", &[]), vote_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("not on this poll's allowlist"));
+}
+
+#[test]
+fn test_cast_vote_with_composite_eligibility() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll for "token holder AND (board OR faculty)"
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Governance Poll".to_string(), "Members on the board or faculty".to_string(), options.clone());
+    if let ExecuteMsg::MakePoll { eligibility, .. } = &mut make_poll_msg {
+        *eligibility = Some(EligibilityRule::All { rules: vec![
+            EligibilityRule::Snip20Balance {
+                requirement: Snip20Requirement {
+                    contract_address: Addr::unchecked("secret1snip20token"),
+                    code_hash: "snip20_code_hash".to_string(),
+                    min_balance: Uint128::new(100),
+                    weight_by_balance: false,
+                },
+            },
+            EligibilityRule::Any { rules: vec![
+                traits_rule(vec![TraitRequirement { trait_type: "role".to_string(), value: "board".to_string() }]),
+                traits_rule(vec![TraitRequirement { trait_type: "role".to_string(), value: "faculty".to_string() }]),
+            ] },
+        ] });
+    }
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Governance Poll", &options);
+    
+    // Voter holds 250 tokens and an SCT with role=faculty
+    let vote_msg = ExecuteMsg::CastVote {
+        poll_id,
+        revision: 0,
+        option_idx: 0,
+        sct_viewing_key: "valid_key".to_string(),
+        snip20_auth: None,
+        merkle_proof: None,
+        credentials: Some(vec![ContractCredential {
+            contract_address: Addr::unchecked("secret1snip20token"),
+            auth: QueryAuth::ViewingKey { key: "valid_key".to_string() },
+        }]),
+        hide_option: None,
+        padding: None,
+    };
+    let result = execute(deps.as_mut(), env, mock_info("voter", &[]), vote_msg);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_cast_vote_no_eligibility_alternative_met() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll for "board OR committee"
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Committee Poll".to_string(), "Board or committee only".to_string(), options.clone());
+    if let ExecuteMsg::MakePoll { eligibility, .. } = &mut make_poll_msg {
+        *eligibility = Some(EligibilityRule::Any { rules: vec![
+            traits_rule(vec![TraitRequirement { trait_type: "role".to_string(), value: "board".to_string() }]),
+            traits_rule(vec![TraitRequirement { trait_type: "role".to_string(), value: "committee".to_string() }]),
+        ] });
+    }
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Committee Poll", &options);
+    
+    // Voter's SCT has role=faculty, which meets neither alternative
+    let vote_msg = new_vote_msg(poll_id, 0, "valid_key".to_string());
+    let result = execute(deps.as_mut(), env, mock_info("voter", &[]), vote_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("None of the eligibility alternatives were met"));
+}
+
+// Helper function to sign permit params with a test key, the same way a wallet signs a query permit
+fn sign_permit<P: Permissions>(params: PermitParams<P>, secret_key: &SecretKey) -> Permit<P> {
+    let secp = Secp256k1::new();
+    let sign_bytes = to_binary(&SignedPermit::from_params(&params)).unwrap();
+    let message = Message::from_slice(&Sha256::digest(sign_bytes.as_slice())).unwrap();
+    let signature = secp.sign_ecdsa(&message, secret_key).serialize_compact();
+    Permit {
+        params,
+        signature: PermitSignature {
+            pub_key: PubKey {
+                r#type: "tendermint/PubKeySecp256k1".to_string(),
+                value: Binary::from(secret_key.public_key(&secp).serialize().to_vec()),
+            },
+            signature: Binary::from(signature.to_vec()),
+        },
+    }
+}
+
+// Helper function to create a RelayVote message signed by the given key, for a poll created at mock_env's time
+fn new_relay_vote_msg(poll_id: &str, option_idx: u32, nonce: &str, secret_key: &SecretKey) -> ExecuteMsg {
+    let env = mock_env();
+    let ballot = sign_permit(PermitParams {
+        allowed_tokens: vec![env.contract.address.to_string()],
+        permit_name: "ballot".to_string(),
+        chain_id: env.block.chain_id.clone(),
+        permissions: vec![Ballot {
+            poll_id: poll_id.to_string(),
+            created_at: env.block.time,
+            revision: 0,
+            option_idx,
+            nonce: nonce.to_string(),
+        }],
+    }, secret_key);
+    let sct_permit = sign_permit(PermitParams {
+        allowed_tokens: vec!["secret1sctcontract".to_string()],
+        permit_name: "sct_permit".to_string(),
+        chain_id: env.block.chain_id,
+        permissions: vec![TokenPermissions::Owner],
+    }, secret_key);
+    ExecuteMsg::RelayVote {
+        ballot,
+        sct_permit,
+        snip20_auth: None,
+        merkle_proof: None,
+        credentials: None,
+        hide_option: None,
+        padding: None,
+    }
+}
+
+#[test]
+fn test_relay_vote() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    let voter_key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let make_poll_msg = new_poll_msg("Relay Poll".to_string(), "Voted through a relayer".to_string(), options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Relay Poll", &options);
+    
+    // A relayer submits the voter's signed ballot
+    let relay_msg = new_relay_vote_msg(&poll_id, 1, "nonce1", &voter_key);
+    let result = execute(deps.as_mut(), env.clone(), mock_info("relayer", &[]), relay_msg.clone());
+    assert!(result.is_ok());
+    
+    // The same ballot can't be submitted again, and neither can a new ballot from the same voter
+    let result = execute(deps.as_mut(), env.clone(), mock_info("relayer", &[]), relay_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("You have already voted on this poll"));
+    let relay_msg = new_relay_vote_msg(&poll_id, 0, "nonce2", &voter_key);
+    let result = execute(deps.as_mut(), env.clone(), mock_info("another_relayer", &[]), relay_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("You have already voted on this poll"));
+    
+    // The vote is recorded for the ballot's signer, not the relayer
+    let permit = sign_permit(PermitParams {
+        allowed_tokens: vec![env.contract.address.to_string()],
+        permit_name: "my_vote".to_string(),
+        chain_id: env.block.chain_id.clone(),
+        permissions: vec![PollPermissions::Votes],
+    }, &voter_key);
+    let query_msg = QueryMsg::WithPermit { permit, query: QueryWithPermit::GetMyVote { poll_id } };
+    let answer: QueryAnswer = from_binary(&query(deps.as_ref(), env, query_msg).unwrap()).unwrap();
+    match answer {
+        QueryAnswer::GetMyVote { vote } => assert_eq!(vote, Some(1)),
+        _ => panic!("Unexpected query response"),
+    }
+}
+
+#[test]
+fn test_relay_vote_invalid_ballot() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    let voter_key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+    let other_key = SecretKey::from_slice(&[9u8; 32]).unwrap();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let make_poll_msg = new_poll_msg("Relay Poll".to_string(), "Voted through a relayer".to_string(), options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Relay Poll", &options);
+    
+    // A relayer can't change the voter's choice
+    let mut relay_msg = new_relay_vote_msg(&poll_id, 1, "nonce1", &voter_key);
+    if let ExecuteMsg::RelayVote { ballot, .. } = &mut relay_msg {
+        ballot.params.permissions[0].option_idx = 0;
+    }
+    let result = execute(deps.as_mut(), env.clone(), mock_info("relayer", &[]), relay_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Failed to verify signatures"));
+    
+    // The SCT permit must come from the ballot's signer
+    let mut relay_msg = new_relay_vote_msg(&poll_id, 1, "nonce1", &voter_key);
+    if let (ExecuteMsg::RelayVote { sct_permit, .. }, ExecuteMsg::RelayVote { sct_permit: other_permit, .. }) =
+        (&mut relay_msg, new_relay_vote_msg(&poll_id, 1, "nonce1", &other_key))
+    {
+        *sct_permit = other_permit;
+    }
+    let result = execute(deps.as_mut(), env.clone(), mock_info("relayer", &[]), relay_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Permit was not signed by the voter"));
+    
+    // A ballot for a deleted poll can't be cast on a new poll with the same poll_id
+    let delete_msg = ExecuteMsg::DeletePoll { poll_id: poll_id.clone() };
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), delete_msg).unwrap();
+    let mut later_env = env;
+    later_env.block.time = later_env.block.time.plus_seconds(60);
+    let make_poll_msg = new_poll_msg("Relay Poll".to_string(), "Voted through a relayer".to_string(), options);
+    execute(deps.as_mut(), later_env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let relay_msg = new_relay_vote_msg(&poll_id, 1, "nonce1", &voter_key);
+    let result = execute(deps.as_mut(), later_env, mock_info("relayer", &[]), relay_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Ballot was signed for an earlier poll"));
+}
+
+#[test]
+fn test_get_my_vote_permit_permissions_and_revocation() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    let voter_key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    let new_permit = |permissions: Vec<PollPermissions>| sign_permit(PermitParams {
+        allowed_tokens: vec![env.contract.address.to_string()],
+        permit_name: "my_permit".to_string(),
+        chain_id: env.block.chain_id.clone(),
+        permissions,
+    }, &voter_key);
+    let get_my_vote = |permit: Permit<PollPermissions>| QueryMsg::WithPermit {
+        permit,
+        query: QueryWithPermit::GetMyVote { poll_id: "poll_id".to_string() },
+    };
+    
+    // A permit without the votes permission can't read votes
+    let result = query(deps.as_ref(), env.clone(), get_my_vote(new_permit(vec![])));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("does not have the votes permission"));
+    
+    // A permit with the votes permission can
+    let permit = new_permit(vec![PollPermissions::Votes]);
+    let result = query(deps.as_ref(), env.clone(), get_my_vote(permit.clone()));
+    assert!(result.is_ok());
+    
+    // Once the signer revokes it, it can't be used anymore
+    let signer = secret_toolkit::permit::validate(deps.as_ref(), "", &permit, env.contract.address.to_string(), None).unwrap();
+    let revoke_msg = ExecuteMsg::RevokePermit { permit_name: "my_permit".to_string() };
+    execute(deps.as_mut(), env.clone(), mock_info(&signer, &[]), revoke_msg).unwrap();
+    let result = query(deps.as_ref(), env, get_my_vote(permit));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("was revoked"));
+}
+
+#[test]
+fn test_get_my_vote_with_viewing_key() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll and vote on it
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Test Poll", &options);
+    let vote_msg = new_vote_msg(poll_id.clone(), 1, "valid_key".to_string());
+    execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), vote_msg).unwrap();
+    
+    // Create a viewing key (returned in the padded response data)
+    let create_msg = ExecuteMsg::CreateViewingKey { entropy: "some entropy".to_string(), padding: None };
+    let response = execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), create_msg).unwrap();
+    let key = match from_binary(&response.data.unwrap()).unwrap() {
+        ExecuteAnswer::CreateViewingKey { key } => key,
+    };
+    
+    // The voter can read their vote with the key
+    let query_msg = QueryMsg::WithViewingKey {
+        address: Addr::unchecked("voter"),
+        key: key.clone(),
+        query: QueryWithPermit::GetMyVote { poll_id: poll_id.clone() },
+    };
+    let answer: QueryAnswer = from_binary(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+    match answer {
+        QueryAnswer::GetMyVote { vote } => assert_eq!(vote, Some(1)),
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // The old key stops working once the voter sets a new one
+    let set_msg = ExecuteMsg::SetViewingKey { key: "new_key".to_string(), padding: None };
+    execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), set_msg).unwrap();
+    let query_msg = QueryMsg::WithViewingKey {
+        address: Addr::unchecked("voter"),
+        key,
+        query: QueryWithPermit::GetMyVote { poll_id },
+    };
+    let result = query(deps.as_ref(), env, query_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Wrong viewing key"));
+}
+
+#[test]
+fn test_get_my_votes() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create two polls and vote on the first
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    for title in ["Poll One", "Poll Two"] {
+        let make_poll_msg = new_poll_msg(title.to_string(), "This is a test poll".to_string(), options.clone());
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    }
+    let poll_one = generate_poll_id("Poll One", &options);
+    let poll_two = generate_poll_id("Poll Two", &options);
+    let vote_msg = new_vote_msg(poll_one.clone(), 1, "valid_key".to_string());
+    execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), vote_msg).unwrap();
+    
+    // And answer a survey
+    let questions = ["Are you happy at work?", "Would you recommend working here?"]
+        .iter()
+        .map(|question| Question {
+            question: question.to_string(),
+            options: vec!["Yes".to_string(), "No".to_string()],
+            method: None,
+            condition: None,
+        })
+        .collect();
+    let response = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), new_survey_msg("Staff Survey", questions)).unwrap();
+    let survey_id = response.attributes.iter().find(|attr| attr.key == "poll_id").unwrap().value.clone();
+    let ballot_msg = new_ballot_msg(&survey_id, vec![vec![0], vec![1]]);
+    execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), ballot_msg).unwrap();
+    let set_msg = ExecuteMsg::SetViewingKey { key: "my_key".to_string(), padding: None };
+    execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), set_msg).unwrap();
+    let with_key = |query: QueryWithPermit| QueryMsg::WithViewingKey {
+        address: Addr::unchecked("voter"),
+        key: "my_key".to_string(),
+        query,
+    };
+    
+    // Votes on several polls come back in one query
+    let query_msg = with_key(QueryWithPermit::GetMyVotes { poll_ids: vec![poll_one.clone(), poll_two.clone()] });
+    let answer: QueryAnswer = from_binary(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+    match answer {
+        QueryAnswer::GetMyVotes { votes } => assert_eq!(votes, vec![
+            PollVote { poll_id: poll_one.clone(), vote: Some(1), answers: None },
+            PollVote { poll_id: poll_two, vote: None, answers: None },
+        ]),
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // Only voted polls are listed, with the ballots of answered surveys
+    let query_msg = with_key(QueryWithPermit::GetMyVotedPolls { page: None, page_size: None });
+    let answer: QueryAnswer = from_binary(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+    match answer {
+        QueryAnswer::GetMyVotedPolls { votes, total } => {
+            assert_eq!(total, 2);
+            assert_eq!(votes, vec![
+                PollVote { poll_id: poll_one, vote: Some(1), answers: None },
+                PollVote { poll_id: survey_id, vote: None, answers: Some(vec![vec![0], vec![1]]) },
+            ]);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // Batches are capped
+    let query_msg = with_key(QueryWithPermit::GetMyVotes { poll_ids: vec!["poll_id".to_string(); 51] });
+    let result = query(deps.as_ref(), env, query_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Too many poll_ids"));
+}
+
+#[test]
+fn test_get_my_polls() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    
+    // The creator sees the poll in their list, and other users don't
+    for (user, expected_total) in [("creator", 1), ("someone_else", 0)] {
+        let set_msg = ExecuteMsg::SetViewingKey { key: "my_key".to_string(), padding: None };
+        execute(deps.as_mut(), env.clone(), mock_info(user, &[]), set_msg).unwrap();
+        let query_msg = QueryMsg::WithViewingKey {
+            address: Addr::unchecked(user),
+            key: "my_key".to_string(),
+            query: QueryWithPermit::GetMyPolls { page: None, page_size: None },
+        };
+        let answer: QueryAnswer = from_binary(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        match answer {
+            QueryAnswer::GetMyPolls { polls, total } => {
+                assert_eq!(total, expected_total);
+                assert_eq!(polls.len(), expected_total as usize);
+            }
+            _ => panic!("Unexpected query response"),
+        }
+    }
+}
+
+#[test]
+fn test_organiser_authored_poll() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract (the instantiator is the admin)
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    
+    // Only the admin can register organisers
+    let set_msg = ExecuteMsg::SetOrganiser {
+        name: "Student Union".to_string(),
+        addresses: vec![Addr::unchecked("union_officer")],
+    };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("union_officer", &[]), set_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Only the admin can do this"));
+    execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), set_msg).unwrap();
+    
+    let answer: QueryAnswer = from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::GetOrganisers {}).unwrap()).unwrap();
+    match answer {
+        QueryAnswer::GetOrganisers { organisers } => assert_eq!(organisers, vec![Organiser {
+            name: "Student Union".to_string(),
+            addresses: vec![Addr::unchecked("union_officer")],
+        }]),
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // Unregistered users can't post as the organiser
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options);
+    if let ExecuteMsg::MakePoll { public_author, .. } = &mut make_poll_msg {
+        *public_author = Some(PublicAuthor::Organiser { name: "Student Union".to_string() });
+    }
+    let result = execute(deps.as_mut(), env.clone(), mock_info("impostor", &[]), make_poll_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("not a verified address of organiser"));
+    
+    // Registered users can, and the poll shows the organiser
+    execute(deps.as_mut(), env.clone(), mock_info("union_officer", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Test Poll", &["Option A".to_string(), "Option B".to_string()]);
+    let poll = POLLS.get(&deps.storage, &poll_id).unwrap();
+    assert_eq!(poll.author, Some(PollAuthor::Organiser { name: "Student Union".to_string() }));
+    
+    // Polls are anonymous by default
+    let options = vec!["Yes".to_string(), "No".to_string()];
+    let make_poll_msg = new_poll_msg("Anonymous Poll".to_string(), "No author shown".to_string(), options.clone());
+    execute(deps.as_mut(), env, mock_info("union_officer", &[]), make_poll_msg).unwrap();
+    let poll = POLLS.get(&deps.storage, &generate_poll_id("Anonymous Poll", &options)).unwrap();
+    assert_eq!(poll.author, None);
+}
+
+#[test]
+fn test_make_poll_requires_sct() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract requiring an SCT to create polls
+    let mut init_msg = get_instantiate_msg();
+    init_msg.require_sct_to_create = Some(true);
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    
+    // Creating a poll without SCT credentials fails
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("sct_auth is required"));
+    
+    // So does creating it with credentials for no SCT
+    let with_key = |key: &str| {
+        let mut make_poll_msg = make_poll_msg.clone();
+        if let ExecuteMsg::MakePoll { sct_auth, .. } = &mut make_poll_msg {
+            *sct_auth = Some(QueryAuth::ViewingKey { key: key.to_string() });
+        }
+        make_poll_msg
+    };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), with_key("invalid_key"));
+    assert!(result.is_err());
+    
+    // SCT holders can create polls
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), with_key("valid_key")).unwrap();
+    
+    // Only the admin can change the config
+    let community_msg = ExecuteMsg::SetCommunityConfig {
+        community: Addr::unchecked("secret1sctcontract"),
+        code_hash: "sct_code_hash".to_string(),
+        require_sct_to_create: Some(false),
+    };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), community_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Only the admin can do this"));
+    
+    // A community override takes precedence over the contract config
+    execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), community_msg).unwrap();
+    let make_poll_msg = new_poll_msg("Open Poll".to_string(), "Anyone can create this".to_string(), options);
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    
+    let answer: QueryAnswer = from_binary(&query(deps.as_ref(), env, QueryMsg::GetConfig {}).unwrap()).unwrap();
+    match answer {
+        QueryAnswer::GetConfig { config, communities } => {
+            assert!(config.require_sct_to_create);
+            assert_eq!(communities, vec![CommunityConfig {
+                community: Addr::unchecked("secret1sctcontract"),
+                code_hash: "sct_code_hash".to_string(),
+                require_sct_to_create: false,
+            }]);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+}
+
+#[test]
+fn test_make_poll_on_foreign_nft_requires_sct() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract requiring an SCT to create polls
+    let mut init_msg = get_instantiate_msg();
+    init_msg.require_sct_to_create = Some(true);
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    
+    // Gating a poll on an unregistered NFT contract, with credentials for it, doesn't get around the requirement
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options);
+    if let ExecuteMsg::MakePoll { eligibility, credentials, .. } = &mut make_poll_msg {
+        let contract = Snip721Contract {
+            address: Addr::unchecked("secret1creatornft"),
+            code_hash: "creator_nft_hash".to_string(),
+        };
+        *eligibility = Some(EligibilityRule::HoldsNft { contract: Some(contract) });
+        *credentials = Some(vec![ContractCredential {
+            contract_address: Addr::unchecked("secret1creatornft"),
+            auth: QueryAuth::ViewingKey { key: "any_key".to_string() },
+        }]);
+    }
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("sct_auth is required"));
+    
+    // The creator is checked against the SCT contract instead
+    if let ExecuteMsg::MakePoll { sct_auth, .. } = &mut make_poll_msg {
+        *sct_auth = Some(QueryAuth::ViewingKey { key: "invalid_key".to_string() });
+    }
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg.clone());
+    assert!(result.is_err());
+    if let ExecuteMsg::MakePoll { sct_auth, .. } = &mut make_poll_msg {
+        *sct_auth = Some(QueryAuth::ViewingKey { key: "valid_key".to_string() });
+    }
+    execute(deps.as_mut(), env, mock_info("creator", &[]), make_poll_msg).unwrap();
+}
+
+#[test]
+fn test_make_poll_with_several_communities_requires_sct() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract requiring an SCT to create polls, except for a registered community
+    let mut init_msg = get_instantiate_msg();
+    init_msg.require_sct_to_create = Some(true);
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    let community_msg = ExecuteMsg::SetCommunityConfig {
+        community: Addr::unchecked("secret1opencommunity"),
+        code_hash: "open_community_hash".to_string(),
+        require_sct_to_create: Some(false),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), community_msg).unwrap();
+    
+    // Naming the open community first doesn't get around the requirement for polls also open to SCT holders
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options);
+    if let ExecuteMsg::MakePoll { eligibility, .. } = &mut make_poll_msg {
+        let contract = Snip721Contract {
+            address: Addr::unchecked("secret1opencommunity"),
+            code_hash: "open_community_hash".to_string(),
+        };
+        *eligibility = Some(EligibilityRule::Any { rules: vec![
+            EligibilityRule::HoldsNft { contract: Some(contract) },
+            EligibilityRule::HoldsNft { contract: None },
+        ] });
+    }
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("sct_auth is required"));
+    
+    // SCT holders can still create it
+    if let ExecuteMsg::MakePoll { sct_auth, .. } = &mut make_poll_msg {
+        *sct_auth = Some(QueryAuth::ViewingKey { key: "valid_key".to_string() });
+    }
+    execute(deps.as_mut(), env, mock_info("creator", &[]), make_poll_msg).unwrap();
+}
+
+#[test]
+fn test_creation_fee_and_deposit() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract with a 1 SCRT fee and a 5 SCRT deposit refunded after 1 vote
+    let mut init_msg = get_instantiate_msg();
+    init_msg.creation_fee = Some(Uint128::new(1_000_000));
+    init_msg.creation_deposit = Some(Uint128::new(5_000_000));
+    init_msg.deposit_refund_turnout = Some(1);
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    
+    // Creating a poll without the fee and deposit fails
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &coins(1_000_000, "uscrt")), make_poll_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Poll creation requires 6000000 uscrt"));
+    
+    // Creating it with both succeeds
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &coins(6_000_000, "uscrt")), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Test Poll", &options);
+    let answer: QueryAnswer = from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::GetTreasury {}).unwrap()).unwrap();
+    match answer {
+        QueryAnswer::GetTreasury { treasury } => {
+            assert_eq!(treasury.fees, Uint128::new(1_000_000));
+            assert_eq!(treasury.deposits_held, Uint128::new(5_000_000));
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // The deposit can't be reclaimed before the poll reaches the refund turnout
+    let claim_msg = ExecuteMsg::ClaimDeposit { poll_id: poll_id.clone() };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), claim_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Poll has 0 votes"));
+    
+    // After a vote, only the depositor can reclaim it
+    let vote_msg = new_vote_msg(poll_id.clone(), 0, "valid_key".to_string());
+    execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), vote_msg).unwrap();
+    let result = execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), claim_msg.clone());
+    assert!(result.is_err());
+    let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), claim_msg.clone()).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "creator".to_string(),
+        amount: coins(5_000_000, "uscrt"),
+    }));
+    
+    // A deposit can only be claimed once
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), claim_msg);
+    assert!(result.is_err());
+    
+    // The admin can withdraw the collected fees, but not more
+    let withdraw_msg = ExecuteMsg::WithdrawFees { amount: Some(Uint128::new(2_000_000)), recipient: None };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), withdraw_msg);
+    assert!(result.is_err());
+    let withdraw_msg = ExecuteMsg::WithdrawFees { amount: None, recipient: Some(Addr::unchecked("treasurer")) };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), withdraw_msg.clone());
+    assert!(result.is_err());
+    let res = execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), withdraw_msg).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "treasurer".to_string(),
+        amount: coins(1_000_000, "uscrt"),
+    }));
+    let answer: QueryAnswer = from_binary(&query(deps.as_ref(), env, QueryMsg::GetTreasury {}).unwrap()).unwrap();
+    match answer {
+        QueryAnswer::GetTreasury { treasury } => {
+            assert!(treasury.fees.is_zero());
+            assert!(treasury.deposits_held.is_zero());
+        }
+        _ => panic!("Unexpected query response"),
+    }
+}
+
+#[test]
+fn test_creator_rate_limit() {
+    let mut deps = mock_dependencies_with_sct();
+    let mut env = mock_env();
+    
+    // A rate limit needs a window
+    let mut init_msg = get_instantiate_msg();
+    init_msg.max_polls_per_window = Some(2);
+    let result = instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("rate_limit_window must be set"));
+    
+    // Instantiate contract allowing 2 polls per hour
+    init_msg.rate_limit_window = Some(3600);
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    let set_config_msg = ExecuteMsg::SetConfig {
+        require_sct_to_create: None,
+        creation_fee: None,
+        creation_deposit: None,
+        deposit_refund_turnout: None,
+        max_polls_per_window: None,
+        rate_limit_window: Some(0),
+        max_open_polls: None,
+        flag_threshold: None,
+    };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), set_config_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("rate_limit_window must be set"));
+    
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let poll_msg = |title: &str| new_poll_msg(title.to_string(), "This is a test poll".to_string(), options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), poll_msg("Poll 1")).unwrap();
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), poll_msg("Poll 2")).unwrap();
+    
+    // A third poll in the same hour fails, but other creators aren't affected
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), poll_msg("Poll 3"));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Too many polls created recently"));
+    execute(deps.as_mut(), env.clone(), mock_info("someone_else", &[]), poll_msg("Poll 4")).unwrap();
+    
+    // It succeeds once the window has passed
+    env.block.time = env.block.time.plus_seconds(3600);
+    execute(deps.as_mut(), env, mock_info("creator", &[]), poll_msg("Poll 3")).unwrap();
+}
+
+#[test]
+fn test_open_poll_quota_and_closing() {
+    let mut deps = mock_dependencies_with_sct();
+    let mut env = mock_env();
+    
+    // Instantiate contract allowing 1 open poll per creator
+    let mut init_msg = get_instantiate_msg();
+    init_msg.max_open_polls = Some(1);
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let closing_poll_msg = |title: &str| {
+        let mut make_poll_msg = new_poll_msg(title.to_string(), "This is a test poll".to_string(), options.clone());
+        if let ExecuteMsg::MakePoll { duration_seconds, .. } = &mut make_poll_msg {
+            *duration_seconds = Some(86400);
+        }
+        make_poll_msg
+    };
+    
+    // Polls that never close can't be made while open polls are limited
+    let make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("duration_seconds is required"));
+    
+    // Create a poll that closes after a day
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), closing_poll_msg("Test Poll")).unwrap();
+    let poll_id = generate_poll_id("Test Poll", &options);
+    
+    // A second poll fails while the first is open
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), closing_poll_msg("Second Poll"));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Too many open polls"));
+    
+    // Once the first poll closes, it can't be voted on, and the creator can make another poll
+    env.block.time = env.block.time.plus_seconds(86400);
+    let vote_msg = new_vote_msg(poll_id, 0, "valid_key".to_string());
+    let result = execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), vote_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Poll is closed"));
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), closing_poll_msg("Second Poll")).unwrap();
+    
+    // Deleting the open poll frees the quota
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), closing_poll_msg("Third Poll"));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Too many open polls"));
+    let delete_msg = ExecuteMsg::DeletePoll { poll_id: generate_poll_id("Second Poll", &options) };
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), delete_msg).unwrap();
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), closing_poll_msg("Third Poll")).unwrap();
+    
+    // So does a moderator hiding it
+    let hide_msg = ExecuteMsg::HidePoll { poll_id: generate_poll_id("Third Poll", &options), reason: "Spam".to_string() };
+    execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), hide_msg).unwrap();
+    execute(deps.as_mut(), env, mock_info("creator", &[]), closing_poll_msg("Fourth Poll")).unwrap();
+}
+
+#[test]
+fn test_flag_poll() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract hiding polls with 2 flags
+    let mut init_msg = get_instantiate_msg();
+    init_msg.flag_threshold = Some(2);
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let make_poll_msg = new_poll_msg("Spam Poll".to_string(), "This is a spam poll".to_string(), options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Spam Poll", &options);
+    let flag_msg = |reason_code: u8, key: &str| ExecuteMsg::FlagPoll {
+        poll_id: poll_id.clone(),
+        reason_code,
+        sct_viewing_key: key.to_string(),
+        snip20_auth: None,
+        merkle_proof: None,
+        credentials: None,
+        padding: None,
+    };
+    
+    // Only eligible voters can flag, with a valid reason code
+    let result = execute(deps.as_mut(), env.clone(), mock_info("flagger1", &[]), flag_msg(0, "invalid_key"));
+    assert!(result.is_err());
+    let result = execute(deps.as_mut(), env.clone(), mock_info("flagger1", &[]), flag_msg(9, "valid_key"));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Invalid reason code"));
+    
+    // Each user can flag a poll once
+    execute(deps.as_mut(), env.clone(), mock_info("flagger1", &[]), flag_msg(0, "valid_key")).unwrap();
+    let result = execute(deps.as_mut(), env.clone(), mock_info("flagger1", &[]), flag_msg(1, "valid_key"));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("already flagged"));
+    
+    let get_poll = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+        let query_msg = QueryMsg::GetPoll { poll_id: poll_id.clone() };
+        match from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap() {
+            QueryAnswer::GetPoll { poll, hidden } => (poll.flags, hidden),
+            _ => panic!("Unexpected query response"),
+        }
+    };
+    assert_eq!(get_poll(&deps), (1, false));
+    
+    // The poll is hidden once it reaches the threshold, but is still reachable by ID
+    execute(deps.as_mut(), env.clone(), mock_info("flagger2", &[]), flag_msg(1, "valid_key")).unwrap();
+    assert_eq!(get_poll(&deps), (2, true));
+    
+    let query_msg = QueryMsg::GetPollFlags { poll_id: poll_id.clone() };
+    match from_binary(&query(deps.as_ref(), env, query_msg).unwrap()).unwrap() {
+        QueryAnswer::GetPollFlags { reasons } => assert_eq!(reasons, vec![1, 1, 0, 0, 0]),
+        _ => panic!("Unexpected query response"),
+    }
+}
+
+#[test]
+fn test_moderation_and_audit_log() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract with a 5 SCRT deposit, hiding polls with 1 flag
+    let mut init_msg = get_instantiate_msg();
+    init_msg.creation_deposit = Some(Uint128::new(5_000_000));
+    init_msg.flag_threshold = Some(1);
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &coins(5_000_000, "uscrt")), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Test Poll", &options);
+    let is_hidden = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+        let query_msg = QueryMsg::GetPoll { poll_id: poll_id.clone() };
+        match from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap() {
+            QueryAnswer::GetPoll { hidden, .. } => hidden,
+            _ => panic!("Unexpected query response"),
+        }
+    };
+    
+    // Only the admin can appoint moderators, and only moderators can hide polls
+    let hide_msg = ExecuteMsg::HidePoll { poll_id: poll_id.clone(), reason: "Abusive wording".to_string() };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("moderator", &[]), hide_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Only moderators can do this"));
+    let set_msg = ExecuteMsg::SetModerators { moderators: vec![Addr::unchecked("moderator")] };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("moderator", &[]), set_msg.clone());
+    assert!(result.is_err());
+    execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), set_msg).unwrap();
+    
+    // A reason is required
+    let no_reason_msg = ExecuteMsg::HidePoll { poll_id: poll_id.clone(), reason: " ".to_string() };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("moderator", &[]), no_reason_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("A reason is required"));
+    
+    // Hiding a poll forfeits its deposit
+    execute(deps.as_mut(), env.clone(), mock_info("moderator", &[]), hide_msg).unwrap();
+    assert!(is_hidden(&deps));
+    match from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::GetTreasury {}).unwrap()).unwrap() {
+        QueryAnswer::GetTreasury { treasury } => assert_eq!(treasury.fees, Uint128::new(5_000_000)),
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // A restored poll stays visible even when flagged, but its deposit isn't given back
+    let restore_msg = ExecuteMsg::RestorePoll { poll_id: poll_id.clone(), reason: "Wording was fixed".to_string() };
+    execute(deps.as_mut(), env.clone(), mock_info("moderator", &[]), restore_msg).unwrap();
+    let claim_msg = ExecuteMsg::ClaimDeposit { poll_id: poll_id.clone() };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), claim_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("No deposit is held for this poll"));
+    let flag_msg = ExecuteMsg::FlagPoll {
+        poll_id: poll_id.clone(),
+        reason_code: 1,
+        sct_viewing_key: "valid_key".to_string(),
+        snip20_auth: None,
+        merkle_proof: None,
+        credentials: None,
+        padding: None,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("flagger", &[]), flag_msg).unwrap();
+    assert!(!is_hidden(&deps));
+    
+    // Every action is in the audit log
+    let query_msg = QueryMsg::GetAuditLog { page: None, page_size: None };
+    match from_binary(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap() {
+        QueryAnswer::GetAuditLog { entries, total } => {
+            assert_eq!(total, 2);
+            assert_eq!(entries[0].action, ModerationAction::Hide);
+            assert_eq!(entries[0].moderator, Addr::unchecked("moderator"));
+            assert_eq!(entries[0].reason, "Abusive wording");
+            assert_eq!(entries[1].action, ModerationAction::Restore);
+            assert_eq!(entries[1].poll_id, poll_id);
+            assert_eq!(entries[1].timestamp, env.block.time);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+}
+
+#[test]
+fn test_edit_poll() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    
+    // Instantiate contract
+    let init_msg = get_instantiate_msg();
+    instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+    
+    // Create a poll with a typo
+    let options = vec!["Option A".to_string(), "Opton B".to_string()];
+    let make_poll_msg = new_poll_msg("Test Pol".to_string(), "This is a test poll".to_string(), options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Test Pol", &options);
+    
+    // Only the creator can edit it
+    let edit_msg = ExecuteMsg::EditPoll {
+        poll_id: poll_id.clone(),
+        title: Some("Test Poll".to_string()),
+        description: None,
+        options: Some(vec!["Option A".to_string(), "Option B".to_string(), "Option C".to_string()]),
+    };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("someone_else", &[]), edit_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Only the poll's creator can do this"));
+    
+    // Invalid content is rejected
+    let invalid_msg = ExecuteMsg::EditPoll { poll_id: poll_id.clone(), title: Some(String::new()), description: None, options: None };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), invalid_msg);
+    assert!(result.is_err());
+    
+    // The edit keeps the poll_id and records the old content
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), edit_msg.clone()).unwrap();
+    match from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::GetPoll { poll_id: poll_id.clone() }).unwrap()).unwrap() {
+        QueryAnswer::GetPoll { poll, .. } => {
+            assert_eq!(poll.poll_id, poll_id);
+            assert_eq!(poll.title, "Test Poll");
+            assert_eq!(poll.description, "This is a test poll");
+            assert_eq!(poll.options.len(), 3);
+            assert_eq!(poll.tally, vec![Uint128::zero(); 3]);
+            assert_eq!(poll.edits, 1);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    match from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::GetPollRevisions { poll_id: poll_id.clone() }).unwrap()).unwrap() {
+        QueryAnswer::GetPollRevisions { revisions } => {
+            assert_eq!(revisions.len(), 1);
+            assert_eq!(revisions[0].title, "Test Pol");
+            assert_eq!(revisions[0].options, options);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // The edited content can't be reused by another poll, or by another edit
+    let new_options = vec!["Option A".to_string(), "Option B".to_string(), "Option C".to_string()];
+    let duplicate_msg = new_poll_msg("Test Poll".to_string(), "A copy".to_string(), new_options.clone());
+    let result = execute(deps.as_mut(), env.clone(), mock_info("someone_else", &[]), duplicate_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Poll ID already taken"));
+    let other_msg = new_poll_msg("Other Poll".to_string(), "Another poll".to_string(), new_options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), other_msg).unwrap();
+    let other_edit_msg = ExecuteMsg::EditPoll {
+        poll_id: generate_poll_id("Other Poll", &new_options),
+        title: Some("Test Poll".to_string()),
+        description: None,
+        options: None,
+    };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), other_edit_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Poll ID already taken"));
+    
+    // Editing it again frees the previous content
+    let retitle_msg = ExecuteMsg::EditPoll { poll_id: poll_id.clone(), title: Some("Test Poll?".to_string()), description: None, options: None };
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), retitle_msg).unwrap();
+    execute(deps.as_mut(), env.clone(), mock_info("someone_else", &[]), duplicate_msg).unwrap();
+    
+    // A vote made before the edits isn't counted against the new options
+    let mut vote_msg = new_vote_msg(poll_id, 1, "valid_key".to_string());
+    let result = execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), vote_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Poll was edited after this vote was made"));
+    
+    // Once someone has voted on the current revision, the poll can't be edited
+    if let ExecuteMsg::CastVote { revision, .. } = &mut vote_msg {
+        *revision = 2;
+    }
+    execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), vote_msg).unwrap();
+    let result = execute(deps.as_mut(), env, mock_info("creator", &[]), edit_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("can't be edited after the first vote"));
+}
+
+#[test]
+fn test_draft_polls() {
+    let mut deps = mock_dependencies_with_sct();
+    let mut env = mock_env();
+    
+    // Instantiate contract with a creation fee
+    let mut init_msg = get_instantiate_msg();
+    init_msg.creation_fee = Some(Uint128::new(1_000_000));
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    
+    // Create a draft (no fee is due yet, and funds are refused) that closes a day after it's published
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Draft Poll".to_string(), "First wording".to_string(), options.clone());
+    if let ExecuteMsg::MakePoll { draft, duration_seconds, .. } = &mut make_poll_msg {
+        *draft = Some(true);
+        *duration_seconds = Some(86400);
+    }
+    let result = execute(deps.as_mut(), env.clone(), mock_info("committee", &coins(1_000_000, "uscrt")), make_poll_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Drafts don't take funds"));
+    execute(deps.as_mut(), env.clone(), mock_info("committee", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Draft Poll", &options);
+    
+    // The draft isn't live
+    let result = query(deps.as_ref(), env.clone(), QueryMsg::GetPoll { poll_id: poll_id.clone() });
+    assert!(result.is_err());
+    match from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::GetNumPolls {}).unwrap()).unwrap() {
+        QueryAnswer::GetNumPolls { num_polls } => assert_eq!(num_polls, 0),
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // The creator can revise it and see it
+    let edit_msg = ExecuteMsg::EditPoll {
+        poll_id: poll_id.clone(),
+        title: None,
+        description: Some("Final wording".to_string()),
+        options: None,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("committee", &[]), edit_msg).unwrap();
+    let my_drafts = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, user: &str| {
+        let query_msg = QueryMsg::WithViewingKey {
+            address: Addr::unchecked(user),
+            key: "my_key".to_string(),
+            query: QueryWithPermit::GetMyDrafts {},
+        };
+        match from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap() {
+            QueryAnswer::GetMyDrafts { drafts } => drafts,
+            _ => panic!("Unexpected query response"),
+        }
+    };
+    for user in ["committee", "someone_else"] {
+        let set_msg = ExecuteMsg::SetViewingKey { key: "my_key".to_string(), padding: None };
+        execute(deps.as_mut(), env.clone(), mock_info(user, &[]), set_msg).unwrap();
+    }
+    let drafts = my_drafts(&deps, "committee");
+    assert_eq!(drafts.len(), 1);
+    assert_eq!(drafts[0].poll.description, "Final wording");
+    assert!(my_drafts(&deps, "someone_else").is_empty());
+    
+    // Only the creator can publish it, with the creation fee
+    let publish_msg = ExecuteMsg::PublishPoll { poll_id: poll_id.clone(), sct_auth: None, credentials: None };
+    let result = execute(deps.as_mut(), env.clone(), mock_info("someone_else", &coins(1_000_000, "uscrt")), publish_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Draft does not exist"));
+    let result = execute(deps.as_mut(), env.clone(), mock_info("committee", &[]), publish_msg.clone());
+    assert!(result.is_err());
+    
+    // Once published, it's live and its duration starts from publication
+    env.block.time = env.block.time.plus_seconds(3600);
+    execute(deps.as_mut(), env.clone(), mock_info("committee", &coins(1_000_000, "uscrt")), publish_msg).unwrap();
+    match from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::GetPoll { poll_id: poll_id.clone() }).unwrap()).unwrap() {
+        QueryAnswer::GetPoll { poll, .. } => {
+            assert_eq!(poll.description, "Final wording");
+            assert_eq!(poll.created_at, env.block.time);
+            assert_eq!(poll.closes_at, Some(env.block.time.plus_seconds(86400)));
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    match from_binary(&query(deps.as_ref(), env, QueryMsg::GetNumPolls {}).unwrap()).unwrap() {
+        QueryAnswer::GetNumPolls { num_polls } => assert_eq!(num_polls, 1),
+        _ => panic!("Unexpected query response"),
+    }
+    assert!(my_drafts(&deps, "committee").is_empty());
+}
+
+#[test]
+fn test_archive_poll() {
+    let mut deps = mock_dependencies_with_sct();
+    let mut env = mock_env();
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), get_instantiate_msg()).unwrap();
+    let set_msg = ExecuteMsg::SetViewingKey { key: "my_key".to_string(), padding: None };
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), set_msg).unwrap();
+    let my_polls_total = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+        let query_msg = QueryMsg::WithViewingKey {
+            address: Addr::unchecked("creator"),
+            key: "my_key".to_string(),
+            query: QueryWithPermit::GetMyPolls { page: None, page_size: None },
+        };
+        match from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap() {
+            QueryAnswer::GetMyPolls { total, .. } => total,
+            _ => panic!("Unexpected query response"),
+        }
+    };
+    
+    // Create a poll that closes after a day, with 3 votes
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    if let ExecuteMsg::MakePoll { duration_seconds, .. } = &mut make_poll_msg {
+        *duration_seconds = Some(86400);
+    }
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Test Poll", &options);
+    for (voter, option_idx) in [("voter1", 0), ("voter2", 1), ("voter3", 1)] {
+        let vote_msg = new_vote_msg(poll_id.clone(), option_idx, "valid_key".to_string());
+        execute(deps.as_mut(), env.clone(), mock_info(voter, &[]), vote_msg).unwrap();
+    }
+    let archive_msg = |limit: u32| ExecuteMsg::ArchivePoll { poll_id: poll_id.clone(), limit: Some(limit) };
+    
+    // Open polls can't be archived
+    let result = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), archive_msg(2));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Only closed polls can be archived"));
+    
+    // Once closed, the votes are removed in batches
+    env.block.time = env.block.time.plus_seconds(86400);
+    assert_eq!(my_polls_total(&deps), 1);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), archive_msg(2)).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "cleaned_up" && attr.value == "false"));
+    assert!(POLLS.get(&deps.storage, &poll_id).is_none());
+    assert_eq!(my_polls_total(&deps), 0);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), archive_msg(2)).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "cleaned_up" && attr.value == "true"));
+    let result = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), archive_msg(2));
+    assert!(result.is_err());
+    
+    // The final result is kept
+    let query_msg = QueryMsg::GetPollResult { poll_id: poll_id.clone() };
+    match from_binary(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap() {
+        QueryAnswer::GetPollResult { result } => {
+            assert_eq!(result.tally, vec![Uint128::new(1), Uint128::new(2)]);
+            assert_eq!(result.voters, 3);
+            assert!(result.cleaned_up);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    let query_msg = QueryMsg::GetPoll { poll_id };
+    assert!(query(deps.as_ref(), env.clone(), query_msg).is_err());
+    match from_binary(&query(deps.as_ref(), env, QueryMsg::GetNumPolls {}).unwrap()).unwrap() {
+        QueryAnswer::GetNumPolls { num_polls } => assert_eq!(num_polls, 0),
+        _ => panic!("Unexpected query response"),
+    }
+}
+
+#[test]
+fn test_archived_poll_id_not_reused() {
+    let mut deps = mock_dependencies_with_sct();
+    let mut env = mock_env();
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), get_instantiate_msg()).unwrap();
+    
+    // Archive a closed poll with a vote, but don't clean it up yet
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    if let ExecuteMsg::MakePoll { duration_seconds, .. } = &mut make_poll_msg {
+        *duration_seconds = Some(86400);
+    }
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg.clone()).unwrap();
+    let poll_id = generate_poll_id("Test Poll", &options);
+    let vote_msg = new_vote_msg(poll_id.clone(), 0, "valid_key".to_string());
+    execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), vote_msg).unwrap();
+    env.block.time = env.block.time.plus_seconds(86400);
+    let archive_msg = ExecuteMsg::ArchivePoll { poll_id: poll_id.clone(), limit: Some(1) };
+    execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), archive_msg).unwrap();
+    
+    // A poll with the same title and options can't take over the archived poll's ID
+    let result = execute(deps.as_mut(), env.clone(), mock_info("someone_else", &[]), make_poll_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Poll ID already taken"));
+    assert!(POLLS.get(&deps.storage, &poll_id).is_none());
+}
+
+#[test]
+fn test_delete_poll() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    let mut init_msg = get_instantiate_msg();
+    init_msg.creation_fee = Some(Uint128::new(1_000_000));
+    init_msg.creation_deposit = Some(Uint128::new(5_000_000));
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), init_msg).unwrap();
+    let set_msg = ExecuteMsg::SetViewingKey { key: "my_key".to_string(), padding: None };
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), set_msg).unwrap();
+    let my_polls_total = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+        let query_msg = QueryMsg::WithViewingKey {
+            address: Addr::unchecked("creator"),
+            key: "my_key".to_string(),
+            query: QueryWithPermit::GetMyPolls { page: None, page_size: None },
+        };
+        match from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap() {
+            QueryAnswer::GetMyPolls { total, .. } => total,
+            _ => panic!("Unexpected query response"),
+        }
+    };
+    
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &coins(6_000_000, "uscrt")), make_poll_msg.clone()).unwrap();
+    assert_eq!(my_polls_total(&deps), 1);
+    let poll_id = generate_poll_id("Test Poll", &options);
+    let delete_msg = ExecuteMsg::DeletePoll { poll_id: poll_id.clone() };
+    
+    // Only the creator can delete the poll
+    let result = execute(deps.as_mut(), env.clone(), mock_info("someone_else", &[]), delete_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Only the poll's creator"));
+    
+    // A poll without votes can be deleted (refunding its deposit), and its ID reused
+    let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), delete_msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    match &res.messages[0].msg {
+        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+            assert_eq!(to_address, "creator");
+            assert_eq!(amount, &coins(5_000_000, "uscrt"));
+        }
+        _ => panic!("Unexpected message"),
+    }
+    assert!(POLLS.get(&deps.storage, &poll_id).is_none());
+    assert_eq!(my_polls_total(&deps), 0);
+    match from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::GetTreasury {}).unwrap()).unwrap() {
+        QueryAnswer::GetTreasury { treasury } => {
+            assert_eq!(treasury.fees, Uint128::new(1_000_000));
+            assert!(treasury.deposits_held.is_zero());
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &coins(6_000_000, "uscrt")), make_poll_msg).unwrap();
+    assert_eq!(my_polls_total(&deps), 1);
+    match from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::GetNumPolls {}).unwrap()).unwrap() {
+        QueryAnswer::GetNumPolls { num_polls } => assert_eq!(num_polls, 1),
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // After the first vote, it can't be
+    let vote_msg = new_vote_msg(poll_id.clone(), 0, "valid_key".to_string());
+    execute(deps.as_mut(), env.clone(), mock_info("voter", &[]), vote_msg).unwrap();
+    let result = execute(deps.as_mut(), env, mock_info("creator", &[]), delete_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("after the first vote"));
+    assert!(POLLS.get(&deps.storage, &poll_id).is_some());
+}
+
+fn new_survey_msg(title: &str, questions: Vec<Question>) -> ExecuteMsg {
+    let mut make_poll_msg = new_poll_msg(title.to_string(), "This is a test survey".to_string(), Vec::new());
+    if let ExecuteMsg::MakePoll { questions: survey_questions, .. } = &mut make_poll_msg {
+        *survey_questions = Some(questions);
+    }
+    make_poll_msg
+}
+
+fn new_ballot_msg(poll_id: &str, answers: Vec<Vec<u32>>) -> ExecuteMsg {
+    ExecuteMsg::SubmitBallot {
+        poll_id: poll_id.to_string(),
+        answers,
+        sct_viewing_key: "valid_key".to_string(),
+        snip20_auth: None,
+        merkle_proof: None,
+        credentials: None,
+        padding: None,
+    }
+}
+
+#[test]
+fn test_survey() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), get_instantiate_msg()).unwrap();
+    
+    // Create a survey with a single choice and a multiple choice question
+    let questions = vec![
+        Question {
+            question: "Are you happy at work?".to_string(),
+            options: vec!["Yes".to_string(), "No".to_string()],
+            method: None,
+            condition: None,
+        },
+        Question {
+            question: "Which perks do you use?".to_string(),
+            options: vec!["Gym".to_string(), "Lunch".to_string(), "Parking".to_string()],
+            method: Some(VotingMethod::MultipleChoice { max_choices: 2 }),
+            condition: None,
+        },
+    ];
+    let response = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), new_survey_msg("Staff Survey", questions)).unwrap();
+    let poll_id = response.attributes.iter().find(|attr| attr.key == "poll_id").unwrap().value.clone();
+    
+    // Surveys are answered with ballots, not single votes
+    let vote_msg = new_vote_msg(poll_id.clone(), 0, "valid_key".to_string());
+    let result = execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), vote_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("use SubmitBallot"));
+    
+    // Ballots must answer every question according to its voting method
+    for (answers, error) in [
+        (vec![vec![0]], "Expected answers to 2 questions"),
+        (vec![vec![0, 1], vec![0]], "between 1 and 1 options"),
+        (vec![vec![0], vec![0, 1, 2]], "between 1 and 2 options"),
+        (vec![vec![0], vec![3]], "Invalid option index"),
+        (vec![vec![0], vec![1, 1]], "same option chosen twice"),
+    ] {
+        let result = execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), new_ballot_msg(&poll_id, answers));
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains(error));
+    }
+    
+    // Each respondent submits one ballot
+    execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), new_ballot_msg(&poll_id, vec![vec![0], vec![0, 1]])).unwrap();
+    execute(deps.as_mut(), env.clone(), mock_info("voter2", &[]), new_ballot_msg(&poll_id, vec![vec![1], vec![1]])).unwrap();
+    let result = execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), new_ballot_msg(&poll_id, vec![vec![1], vec![2]]));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("already submitted a ballot"));
+    
+    // Each question has its own tally
+    let query_msg = QueryMsg::GetPoll { poll_id: poll_id.clone() };
+    match from_binary(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap() {
+        QueryAnswer::GetPoll { poll, .. } => {
+            assert!(poll.tally.is_empty());
+            assert_eq!(poll.questions[0].tally, vec![Uint128::new(1), Uint128::new(1)]);
+            assert_eq!(poll.questions[1].tally, vec![Uint128::new(1), Uint128::new(2), Uint128::zero()]);
+            assert_eq!(poll.questions[1].respondents, 2);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // Respondents can read their own ballot
+    let set_msg = ExecuteMsg::SetViewingKey { key: "voter_key".to_string(), padding: None };
+    execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), set_msg).unwrap();
+    let query_msg = QueryMsg::WithViewingKey {
+        address: Addr::unchecked("voter1"),
+        key: "voter_key".to_string(),
+        query: QueryWithPermit::GetMyBallot { poll_id },
+    };
+    match from_binary(&query(deps.as_ref(), env, query_msg).unwrap()).unwrap() {
+        QueryAnswer::GetMyBallot { answers } => assert_eq!(answers, Some(vec![vec![0], vec![0, 1]])),
+        _ => panic!("Unexpected query response"),
+    }
+}
+
+#[test]
+fn test_survey_branching() {
+    let mut deps = mock_dependencies_with_sct();
+    let env = mock_env();
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), get_instantiate_msg()).unwrap();
+    
+    let question = |text: &str, options: &[&str], condition: Option<QuestionCondition>| Question {
+        question: text.to_string(),
+        options: options.iter().map(|option| option.to_string()).collect(),
+        method: None,
+        condition,
+    };
+    
+    // Conditions can only refer to earlier questions
+    let questions = vec![
+        question("Why not?", &["Pay", "Hours"], Some(QuestionCondition { question: 1, options: vec![1] })),
+        question("Are you happy at work?", &["Yes", "No"], None),
+    ];
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), new_survey_msg("Bad Survey", questions));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("earlier question"));
+    
+    // Q2 is only shown to respondents who answered No to Q1, and Q3 only to those who answered Hours to Q2
+    let questions = vec![
+        question("Are you happy at work?", &["Yes", "No"], None),
+        question("Why not?", &["Pay", "Hours"], Some(QuestionCondition { question: 0, options: vec![1] })),
+        question("How many hours?", &["40-50", "50+"], Some(QuestionCondition { question: 1, options: vec![1] })),
+    ];
+    let response = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), new_survey_msg("Staff Survey", questions)).unwrap();
+    let poll_id = response.attributes.iter().find(|attr| attr.key == "poll_id").unwrap().value.clone();
+    
+    // Answers must follow the branching rules
+    for (answers, error) in [
+        (vec![vec![0], vec![1], vec![]], "Question 1 wasn't shown"),
+        (vec![vec![1], vec![], vec![]], "Question 1 takes between 1 and 1 options"),
+        (vec![vec![1], vec![0], vec![1]], "Question 2 wasn't shown"),
+    ] {
+        let result = execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), new_ballot_msg(&poll_id, answers));
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains(error));
+    }
+    execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), new_ballot_msg(&poll_id, vec![vec![0], vec![], vec![]])).unwrap();
+    execute(deps.as_mut(), env.clone(), mock_info("voter2", &[]), new_ballot_msg(&poll_id, vec![vec![1], vec![0], vec![]])).unwrap();
+    execute(deps.as_mut(), env.clone(), mock_info("voter3", &[]), new_ballot_msg(&poll_id, vec![vec![1], vec![1], vec![1]])).unwrap();
+    
+    // Each question only counts the respondents who were shown it
+    let query_msg = QueryMsg::GetPoll { poll_id };
+    match from_binary(&query(deps.as_ref(), env, query_msg).unwrap()).unwrap() {
+        QueryAnswer::GetPoll { poll, .. } => {
+            let respondents: Vec<u32> = poll.questions.iter().map(|question| question.respondents).collect();
+            assert_eq!(respondents, vec![3, 2, 1]);
+            assert_eq!(poll.questions[1].tally, vec![Uint128::new(1), Uint128::new(1)]);
+            assert_eq!(poll.questions[2].tally, vec![Uint128::zero(), Uint128::new(1)]);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+}
+
+#[test]
+fn test_survey_cross_tab() {
+    let mut deps = mock_dependencies_with_sct();
+    let mut env = mock_env();
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), get_instantiate_msg()).unwrap();
+    
+    // Create a survey that closes after a day
+    let questions = vec![
+        Question {
+            question: "Are you happy at work?".to_string(),
+            options: vec!["Yes".to_string(), "No".to_string(), "Unsure".to_string()],
+            method: None,
+            condition: None,
+        },
+        Question {
+            question: "Which office are you in?".to_string(),
+            options: vec!["North".to_string(), "South".to_string()],
+            method: None,
+            condition: None,
+        },
+    ];
+    let mut make_survey_msg = new_survey_msg("Staff Survey", questions);
+    let cross_tab = |row_question: u32, column_question: u32| CrossTab { row_question, column_question };
+    
+    // Cross-tabs must be two different questions of the survey, listed once
+    for (invalid_cross_tabs, error) in [
+        (vec![cross_tab(1, 1)], "Invalid cross-tab"),
+        (vec![cross_tab(0, 2)], "Invalid cross-tab"),
+        (vec![cross_tab(0, 1), cross_tab(0, 1)], "Duplicate cross-tab"),
+    ] {
+        if let ExecuteMsg::MakePoll { cross_tabs, .. } = &mut make_survey_msg {
+            *cross_tabs = Some(invalid_cross_tabs);
+        }
+        let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_survey_msg.clone());
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains(error));
+    }
+    
+    if let ExecuteMsg::MakePoll { cross_tabs, duration_seconds, .. } = &mut make_survey_msg {
+        *cross_tabs = Some(vec![cross_tab(0, 1)]);
+        *duration_seconds = Some(86400);
+    }
+    let response = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_survey_msg).unwrap();
+    let poll_id = response.attributes.iter().find(|attr| attr.key == "poll_id").unwrap().value.clone();
+    let cross_tab_msg = |row_question: u32, column_question: u32| QueryMsg::GetCrossTab {
+        poll_id: poll_id.clone(),
+        row_question,
+        column_question,
+    };
+    
+    // 5 respondents for each yes/no answer in each office, and 1 unsure respondent in the North
+    let answers = (0..20).map(|i| vec![vec![i / 10], vec![i % 2]])
+        .chain(std::iter::once(vec![vec![2], vec![0]]));
+    for (i, answers) in answers.enumerate() {
+        let ballot_msg = new_ballot_msg(&poll_id, answers);
+        execute(deps.as_mut(), env.clone(), mock_info(&format!("voter{}", i), &[]), ballot_msg).unwrap();
+    }
+    
+    // Cross-tabs aren't available while the survey is open
+    let result = query(deps.as_ref(), env.clone(), cross_tab_msg(0, 1));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("once the survey has closed"));
+    
+    // Once closed, the unsure respondent's cell is suppressed, along with enough other cells
+    // that it can't be worked out from the public tallies
+    env.block.time = env.block.time.plus_seconds(86400);
+    let result = query(deps.as_ref(), env.clone(), cross_tab_msg(1, 0));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("isn't one of the survey's cross-tabs"));
+    let expected_cells = vec![vec![None, None], vec![Some(5), Some(5)], vec![None, None]];
+    match from_binary(&query(deps.as_ref(), env.clone(), cross_tab_msg(0, 1)).unwrap()).unwrap() {
+        QueryAnswer::GetCrossTab { respondents, cells } => {
+            assert_eq!(respondents, 21);
+            assert_eq!(cells, expected_cells);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // Archiving the survey (which removes its ballots) keeps the cross-tab in its final result
+    let archive_msg = ExecuteMsg::ArchivePoll { poll_id: poll_id.clone(), limit: None };
+    execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), archive_msg).unwrap();
+    match from_binary(&query(deps.as_ref(), env.clone(), cross_tab_msg(0, 1)).unwrap()).unwrap() {
+        QueryAnswer::GetCrossTab { respondents, cells } => {
+            assert_eq!(respondents, 21);
+            assert_eq!(cells, expected_cells);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    let result = query(deps.as_ref(), env, cross_tab_msg(1, 0));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_breakdown() {
+    let mut deps = mock_dependencies_with_sct();
+    let mut env = mock_env();
+    instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), get_instantiate_msg()).unwrap();
+    
+    // Create a poll broken down by role, closing after a day
+    let options = vec!["Option A".to_string(), "Option B".to_string()];
+    let mut make_poll_msg = new_poll_msg("Test Poll".to_string(), "This is a test poll".to_string(), options.clone());
+    if let ExecuteMsg::MakePoll { breakdown, duration_seconds, .. } = &mut make_poll_msg {
+        *breakdown = Some(Breakdown {
+            trait_type: "role".to_string(),
+            values: vec!["student".to_string(), "faculty".to_string()],
+        });
+        *duration_seconds = Some(86400);
+    }
+    
+    // Groups are read from the default SCT, so voters must be required to hold one
+    let mut allowlist_poll_msg = make_poll_msg.clone();
+    if let ExecuteMsg::MakePoll { eligibility, .. } = &mut allowlist_poll_msg {
+        *eligibility = Some(EligibilityRule::OnAllowlist { root: "ab".repeat(32) });
+    }
+    let result = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), allowlist_poll_msg);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("must require the default SCT"));
+    
+    execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), make_poll_msg).unwrap();
+    let poll_id = generate_poll_id("Test Poll", &options);
+    
+    // Every mock SCT has role=faculty
+    for i in 0..10 {
+        let vote_msg = new_vote_msg(poll_id.clone(), i % 2, "valid_key".to_string());
+        execute(deps.as_mut(), env.clone(), mock_info(&format!("voter{}", i), &[]), vote_msg).unwrap();
+    }
+    
+    // Breakdowns aren't available while the poll is open
+    let query_msg = QueryMsg::GetBreakdown { poll_id: poll_id.clone() };
+    let result = query(deps.as_ref(), env.clone(), query_msg.clone());
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("once the poll has closed"));
+    
+    // Once closed, the votes are counted by role (students, faculty, then other)
+    env.block.time = env.block.time.plus_seconds(86400);
+    let expected_cells = vec![vec![Some(0), Some(0)], vec![Some(5), Some(5)], vec![Some(0), Some(0)]];
+    match from_binary(&query(deps.as_ref(), env.clone(), query_msg.clone()).unwrap()).unwrap() {
+        QueryAnswer::GetBreakdown { voters, cells } => {
+            assert_eq!(voters, 10);
+            assert_eq!(cells, expected_cells);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    
+    // Archiving keeps the breakdown in the poll's final result
+    let archive_msg = ExecuteMsg::ArchivePoll { poll_id: poll_id.clone(), limit: None };
+    execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), archive_msg).unwrap();
+    match from_binary(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap() {
+        QueryAnswer::GetBreakdown { voters, cells } => {
+            assert_eq!(voters, 10);
+            assert_eq!(cells, expected_cells);
+        }
+        _ => panic!("Unexpected query response"),
+    }
+    match from_binary(&query(deps.as_ref(), env, QueryMsg::GetPollResult { poll_id }).unwrap()).unwrap() {
+        QueryAnswer::GetPollResult { result } => assert_eq!(result.breakdown, Some(expected_cells)),
+        _ => panic!("Unexpected query response"),
+    }
+}
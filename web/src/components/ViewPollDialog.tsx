@@ -105,7 +105,7 @@ const ViewPollDialog = ({ poll: initialPoll, isOpen, onClose }: ViewPollDialogPr
     
     try {
       // Submit vote to blockchain
-      await secretJsFunctions.castVote(poll.pollId, optionIndex);
+      await secretJsFunctions.castVote(poll.pollId, poll.edits, optionIndex);
       
       console.log(`Voted for option ${optionIndex} in poll ${poll.pollId}`);
       
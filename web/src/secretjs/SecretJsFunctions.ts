@@ -25,7 +25,9 @@ type GetPollsResponse = {
                 nanos: number;
             };
             options: string[];
-            tally: number[];
+            // Uint128 values are serialized as strings
+            tally: string[];
+            edits: number;
         }>;
     };
 } | string;
@@ -42,6 +44,17 @@ type GetMyVoteResponse = {
     };
 } | string;
 
+type GetMyVotesResponse = {
+    get_my_votes: {
+        votes: Array<{
+            poll_id: string;
+            vote: number | null;
+            // The ballot, for surveys
+            answers: number[][] | null;
+        }>;
+    };
+} | string;
+
 // SCT contract response types
 type SCTTokensResponse = {
     token_list: {
@@ -93,6 +106,7 @@ const SecretJsFunctions = () => {
     // Execute: Cast a vote on a poll
     const castVote = async (
         pollId: string,
+        revision: number,
         optionIdx: number
     ): Promise<void> => {
         if (!secretJs || !secretAddress) throw new WalletError("no wallet connected");
@@ -117,6 +131,7 @@ const SecretJsFunctions = () => {
             msg: {
                 cast_vote: {
                     poll_id: pollId,
+                    revision,
                     option_idx: optionIdx,
                     sct_viewing_key: sctViewingKey
                 }
@@ -180,7 +195,8 @@ const SecretJsFunctions = () => {
                     optionId: `opt-${index}`,
                     text
                 })),
-                tally: poll.tally
+                tally: poll.tally.map(Number),
+                edits: poll.edits
             };
         });
     };
@@ -236,6 +252,36 @@ const SecretJsFunctions = () => {
         return result.get_my_vote.vote;
     };
 
+    // Query: Get user's votes on several polls at once (requires permit, max 50 polls)
+    const getMyVotes = async (pollIds: string[]): Promise<Record<string, number | null>> => {
+        if (!secretJs || !secretAddress) throw new WalletError("no wallet connected");
+
+        const permit = await getPermit();
+
+        const getMyVotesMsg = {
+            contract_address: contractAddress,
+            query: {
+                with_permit: {
+                    permit,
+                    query: {
+                        get_my_votes: {
+                            poll_ids: pollIds
+                        }
+                    }
+                }
+            },
+            code_hash: contractCodeHash,
+        };
+
+        const result = await secretJs.query.compute.queryContract(getMyVotesMsg) as GetMyVotesResponse;
+
+        if (typeof result === "string") {
+            throw new QueryError(result);
+        }
+
+        return Object.fromEntries(result.get_my_votes.votes.map(({ poll_id, vote }) => [poll_id, vote]));
+    };
+
     // Permit storage key
     const storageKey = `${secretAddress}:${contractAddress}:queryPermit`;
 
@@ -363,6 +409,7 @@ const SecretJsFunctions = () => {
         getPolls,
         getNumPolls,
         getMyVote,
+        getMyVotes,
         createSCTViewingKey,
         checkSCTOwnership,
     };
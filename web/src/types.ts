@@ -22,6 +22,8 @@ export interface Poll {
   options: PollOption[];
   /** Vote count per option index */
   tally: number[];
+  /** Number of times the poll was edited (votes must name it, so they match the options shown) */
+  edits: number;
 }
 
 export interface PollStore {